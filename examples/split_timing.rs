@@ -0,0 +1,77 @@
+//! Times the two ways the FFmpeg backend cuts stream copies: one pass with the segment
+//! muxer (`extract_segments`) against one FFmpeg call per part (`extract_segment`).
+//!
+//! ```bash
+//! cargo run --release --example split_timing -- book.mp3 [MIN_SILENCE_SECS] [NOISE_DB]
+//! ```
+//!
+//! The parts are written to a temporary folder that is removed afterwards.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use audiobook_splitter_rust::{FfmpegBackend, MediaBackend, SplitOutput, Splitter};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(input) = args.first().map(PathBuf::from) else {
+        eprintln!("Usage: split_timing <AUDIO FILE> [MIN_SILENCE_SECS] [NOISE_DB]");
+        return ExitCode::from(2);
+    };
+    let silence_threshold_seconds = args.get(1).and_then(|value| value.parse().ok()).unwrap_or(2.0);
+    let noise_threshold_db = args.get(2).and_then(|value| value.parse().ok()).unwrap_or(-40.0);
+
+    match time_cuts(&input, silence_threshold_seconds, noise_threshold_db) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn time_cuts(input: &PathBuf, silence_threshold_seconds: f64, noise_threshold_db: f64) -> Result<(), String> {
+    let output_dir = std::env::temp_dir().join(format!("audiobook-splitter-timing-{}", std::process::id()));
+    std::fs::create_dir_all(&output_dir).map_err(|e| format!("Failed to create '{}': {}", output_dir.display(), e))?;
+
+    let analysis = Splitter::new(&output_dir)
+        .silence_threshold_seconds(silence_threshold_seconds)
+        .noise_threshold_db(noise_threshold_db)
+        .analyze(input)?;
+    let extension = input.extension().unwrap_or_default().to_string_lossy().to_string();
+    let outputs = |name: &str| -> Vec<SplitOutput> {
+        analysis
+            .segments()
+            .into_iter()
+            .map(|segment| {
+                let path = output_dir.join(format!("{}_{:03}.{}", name, segment.number, extension));
+                SplitOutput { segment, path }
+            })
+            .collect()
+    };
+    println!("'{}': {:.1}s of audio in {} parts.", input.display(), analysis.total_duration, analysis.segments().len());
+
+    let one_pass = outputs("one_pass");
+    let start = Instant::now();
+    let result = FfmpegBackend.extract_segments(input, &one_pass, &|_| {}, &|message| eprintln!("Warning: {}", message));
+    let one_pass_time = start.elapsed();
+
+    let part_by_part = outputs("part_by_part");
+    let start = Instant::now();
+    let result = result.and_then(|()| {
+        part_by_part.iter().try_for_each(|output| FfmpegBackend.extract_segment(input, &output.segment, &output.path))
+    });
+    let part_by_part_time = start.elapsed();
+
+    let _ = std::fs::remove_dir_all(&output_dir);
+    result?;
+    println!("One pass:     {}", format_time(one_pass_time));
+    println!("Part by part: {}", format_time(part_by_part_time));
+    println!("Speedup:      {:.1}x", part_by_part_time.as_secs_f64() / one_pass_time.as_secs_f64().max(f64::EPSILON));
+    Ok(())
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{:.2}s", time.as_secs_f64())
+}
@@ -0,0 +1,757 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::silencedetect::SilenceParser;
+use super::{DetectionEvent, MediaBackend};
+use crate::chapters::{escape_ffmetadata_value, ffmetadata_chapters, parse_ffprobe_chapters, unquote_flat_value, Chapter};
+use crate::concat::{audio_extension, first_audio_file, is_concat_list};
+use crate::silence::Silence;
+use crate::splitter::{Segment, SplitOutput};
+use crate::streams::{parse_ffprobe_streams, MediaStreams};
+use crate::tags::{metadata_block_picture, parse_ffprobe_tags, Tags};
+
+/// Backend that shells out to the `ffmpeg` and `ffprobe` executables found in the `PATH`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FfmpegBackend;
+
+// How many log lines of a failed silence detection are kept for the error message.
+const LOG_TAIL_LINES: usize = 20;
+
+// Output formats that take cover art as an attached picture stream, and those (Ogg)
+// that take it as a `METADATA_BLOCK_PICTURE` comment.
+const ATTACHED_PICTURE_EXTENSIONS: &[&str] = &["mp3", "flac", "m4a", "m4b", "mp4"];
+const OGG_EXTENSIONS: &[&str] = &["ogg", "oga", "opus", "spx"];
+
+impl FfmpegBackend {
+    /// Starts FFmpeg's silence detection on `input` and returns its events, which can be
+    /// read while FFmpeg is still running.
+    pub fn detection_events(
+        &self,
+        input: &Path,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+    ) -> Result<DetectionEvents, String> {
+        // --- Detect silences using FFmpeg's 'silencedetect' filter ---
+        // The filter logs at the info level. The progress report goes to stderr as well,
+        // so a single reader sees progress and silences in the order they happen.
+        let mut child = Command::new("ffmpeg")
+            .args(input_format_args(input))
+            .arg("-i")
+            .arg(input)
+            .arg("-hide_banner")
+            .arg("-nostats")
+            .arg("-loglevel")
+            .arg("info")
+            .arg("-progress")
+            .arg("pipe:2")
+            .arg("-af")
+            .arg(format!("silencedetect=n={}dB:d={}", noise_threshold_db, silence_threshold_seconds))
+            .arg("-f")
+            .arg("null")
+            .arg("-")
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to spawn ffmpeg. Please ensure FFmpeg is installed and in your PATH. Error: {}", e))?;
+
+        let stderr = child.stderr.take().expect("stderr is piped");
+        Ok(DetectionEvents { child, log: LogEvents::new(BufReader::new(stderr)) })
+    }
+}
+
+/// A running FFmpeg silence detection, created with [`FfmpegBackend::detection_events`].
+///
+/// Iterating yields the progress and each silence as soon as FFmpeg reports them, reading
+/// its log line by line. [`finish`](Self::finish) then checks that FFmpeg succeeded.
+/// Dropping the events early stops FFmpeg.
+pub struct DetectionEvents {
+    child: Child,
+    log: LogEvents<BufReader<ChildStderr>>,
+}
+
+impl Iterator for DetectionEvents {
+    type Item = DetectionEvent;
+
+    fn next(&mut self) -> Option<DetectionEvent> {
+        self.log.next()
+    }
+}
+
+// The events in a silencedetect log, read line by line as FFmpeg writes it.
+struct LogEvents<R> {
+    log: R,
+    parser: SilenceParser,
+    position: f64,                              // The last position FFmpeg reported.
+    silences_at_end: Option<VecDeque<Silence>>, // The silences still open when the log ended.
+    log_tail: VecDeque<String>,                 // The last log lines that weren't events, for error messages.
+    error: Option<String>,                      // A parse error that ended the events early.
+}
+
+impl<R: BufRead> LogEvents<R> {
+    fn new(log: R) -> Self {
+        LogEvents { log, parser: SilenceParser::new(), position: 0.0, silences_at_end: None, log_tail: VecDeque::new(), error: None }
+    }
+
+    // The next line of the log, or `None` at its end. FFmpeg prints tags and file names
+    // as they are, so a line needn't be UTF-8 and is read lossily rather than ending the log.
+    fn next_line(&mut self) -> Option<String> {
+        let mut line = Vec::new();
+        match self.log.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_string()),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LogEvents<R> {
+    type Item = DetectionEvent;
+
+    fn next(&mut self) -> Option<DetectionEvent> {
+        if self.error.is_some() {
+            return None;
+        }
+        while let Some(line) = self.next_line() {
+            if let Some(seconds) = parse_progress_line(&line) {
+                self.position = seconds;
+                return Some(DetectionEvent::Progress(seconds));
+            }
+            match self.parser.push_line(&line) {
+                Ok(Some(silence)) => return Some(DetectionEvent::Silence(silence)),
+                Ok(None) => {
+                    self.log_tail.push_back(line);
+                    if self.log_tail.len() > LOG_TAIL_LINES {
+                        self.log_tail.pop_front();
+                    }
+                }
+                Err(e) => {
+                    self.error = Some(e);
+                    return None;
+                }
+            }
+        }
+        // The final progress report is the end of the audio, where open silences end.
+        let position = self.position;
+        let parser = &mut self.parser;
+        self.silences_at_end
+            .get_or_insert_with(|| parser.finish(position).into())
+            .pop_front()
+            .map(DetectionEvent::Silence)
+    }
+}
+
+impl DetectionEvents {
+    /// Reads the rest of FFmpeg's report, waits for it to exit and returns an error if it failed.
+    pub fn finish(mut self) -> Result<(), String> {
+        for _ in self.by_ref() {}
+        if let Some(e) = self.log.error.take() {
+            return Err(e);
+        }
+
+        let status = self.child.wait().map_err(|e| format!("Failed to wait for ffmpeg process: {}", e))?;
+        if !status.success() {
+            let log: Vec<&str> = self.log.log_tail.iter().map(String::as_str).collect();
+            return Err(format!("FFmpeg exited with a non-zero status code during silence detection. Stderr:\n{}", log.join("\n")));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DetectionEvents {
+    fn drop(&mut self) {
+        // Stops FFmpeg if the events weren't read to the end; harmless once it has exited.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl MediaBackend for FfmpegBackend {
+    fn probe_duration(&self, input: &Path) -> Result<f64, String> {
+        // Get the total duration of the input audio file using ffprobe.
+        let total_duration_output = Command::new("ffprobe")
+            .arg("-v")
+            .arg("error")
+            .arg("-show_entries")
+            .arg("format=duration")
+            .arg("-of")
+            .arg("default=noprint_wrappers=1:nokey=1")
+            .args(input_format_args(input))
+            .arg(input)
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to spawn ffprobe. Please ensure FFprobe is installed and in your PATH. Error: {}", e))?
+            .wait_with_output()
+            .map_err(|e| format!("Failed to wait for ffprobe process: {}", e))?;
+
+        if !total_duration_output.status.success() {
+            let stderr_str = String::from_utf8_lossy(&total_duration_output.stderr);
+            return Err(format!("FFprobe exited with a non-zero status code. Stderr:\n{}", stderr_str));
+        }
+
+        let total_duration_str = String::from_utf8_lossy(&total_duration_output.stdout);
+        total_duration_str.trim().parse::<f64>().map_err(|e| format!("Failed to parse total audio duration: {}", e))
+    }
+
+    fn detect_silences(
+        &self,
+        input: &Path,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+    ) -> Result<Vec<Silence>, String> {
+        self.detect_silences_streaming(input, silence_threshold_seconds, noise_threshold_db, &mut |_| {})
+    }
+
+    fn detect_silences_streaming(
+        &self,
+        input: &Path,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+        on_event: &mut dyn FnMut(DetectionEvent),
+    ) -> Result<Vec<Silence>, String> {
+        let mut events = self.detection_events(input, silence_threshold_seconds, noise_threshold_db)?;
+        let mut silences = Vec::new();
+        for event in events.by_ref() {
+            if let DetectionEvent::Silence(silence) = &event {
+                silences.push(*silence);
+            }
+            on_event(event);
+        }
+        events.finish()?;
+        Ok(silences)
+    }
+
+    fn probe_chapters(&self, input: &Path) -> Result<Vec<Chapter>, String> {
+        let output = Command::new("ffprobe")
+            .arg("-v")
+            .arg("error")
+            .arg("-show_chapters")
+            .arg("-of")
+            .arg("flat")
+            .args(input_format_args(input))
+            .arg(input)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to spawn ffprobe. Please ensure FFprobe is installed and in your PATH. Error: {}", e))?
+            .wait_with_output()
+            .map_err(|e| format!("Failed to wait for ffprobe process: {}", e))?;
+
+        if !output.status.success() {
+            let stderr_str = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("FFprobe exited with a non-zero status code while reading chapters. Stderr:\n{}", stderr_str));
+        }
+
+        parse_ffprobe_chapters(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn probe_tags(&self, input: &Path) -> Result<Tags, String> {
+        // Concat lists have no tags of their own; the first joined file speaks for the book.
+        let source = first_audio_file(input);
+        let output = Command::new("ffprobe")
+            .arg("-v")
+            .arg("error")
+            .arg("-show_entries")
+            .arg("format_tags:stream=codec_type:stream_tags:stream_disposition=attached_pic")
+            .arg("-of")
+            .arg("flat")
+            .arg(&source)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to spawn ffprobe. Please ensure FFprobe is installed and in your PATH. Error: {}", e))?
+            .wait_with_output()
+            .map_err(|e| format!("Failed to wait for ffprobe process: {}", e))?;
+
+        if !output.status.success() {
+            let stderr_str = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("FFprobe exited with a non-zero status code while reading tags. Stderr:\n{}", stderr_str));
+        }
+
+        parse_ffprobe_tags(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn probe_audio_format(&self, input: &Path) -> Result<Option<String>, String> {
+        // FFprobe fails on anything it can't demux, which is no audio file then.
+        let Some(output) = probe_stream_listing(input)? else {
+            return Ok(None);
+        };
+        let streams = parse_ffprobe_streams(&output)?;
+        Ok(streams.audio_extension(streams.default_audio_stream()).map(str::to_string))
+    }
+
+    fn probe_streams(&self, input: &Path) -> Result<MediaStreams, String> {
+        match probe_stream_listing(input)? {
+            Some(output) => parse_ffprobe_streams(&output),
+            None => Err(format!("FFprobe can't read the streams of '{}'.", input.display())),
+        }
+    }
+
+    fn extract_audio_stream(&self, input: &Path, stream: usize, output: &Path, encode: bool) -> Result<(), String> {
+        let mut command = Command::new("ffmpeg");
+        command
+            .arg("-v")
+            .arg("error")
+            .args(input_format_args(input))
+            .arg("-i")
+            .arg(input)
+            .arg("-map")
+            .arg(format!("0:a:{}", stream))
+            .arg("-map_metadata")
+            .arg("0")
+            .arg("-map_chapters")
+            .arg("0");
+        // Without `-c:a copy`, FFmpeg encodes with the default encoder of the output format.
+        if !encode {
+            command.arg("-c:a").arg("copy");
+        }
+        let output_result = command
+            .arg("-y")
+            .arg(output)
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| format!("Failed to execute ffmpeg for extracting the audio. Error: {}", e))?;
+
+        if !output_result.status.success() {
+            return Err(format!("FFmpeg failed to extract audio stream {} of '{}'. Stderr:\n{}",
+                               stream + 1, input.display(), String::from_utf8_lossy(&output_result.stderr)));
+        }
+        Ok(())
+    }
+
+    fn write_tags(&self, input: &Path, output: &Path, tags: &Tags) -> Result<(), String> {
+        let source = first_audio_file(input);
+        let extension = output.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+        let cover_art_stream = if tags.cover_art { find_cover_art_stream(&source)? } else { None };
+
+        let mut tags = tags.clone();
+        let mut attached_picture = None;
+        if let Some(stream) = cover_art_stream {
+            if OGG_EXTENSIONS.contains(&extension.as_str()) {
+                tags.set("METADATA_BLOCK_PICTURE", metadata_block_picture(&read_cover_art(&source, stream)?));
+            } else if ATTACHED_PICTURE_EXTENSIONS.contains(&extension.as_str()) {
+                attached_picture = Some(stream);
+            }
+        }
+
+        // The tags are handed to FFmpeg as a metadata file, which has no length limit
+        // (unlike command-line arguments) for embedded pictures.
+        let metadata_path = output.with_extension("tags.txt");
+        let mut metadata = String::from(";FFMETADATA1\n");
+        for (key, value) in &tags.entries {
+            metadata.push_str(&format!("{}={}\n", escape_ffmetadata_value(key), escape_ffmetadata_value(value)));
+        }
+        std::fs::write(&metadata_path, metadata)
+            .map_err(|e| format!("Failed to write tag metadata '{}': {}", metadata_path.display(), e))?;
+
+        // The segment is remuxed next to itself and then replaces the untagged file.
+        let tagged_path = output.with_extension(format!("tagging.{}", extension));
+        let mut command = Command::new("ffmpeg");
+        command
+            .arg("-v")
+            .arg("error")
+            .arg("-i")
+            .arg(output)
+            .arg("-i")
+            .arg(&metadata_path);
+        if attached_picture.is_some() {
+            command.arg("-i").arg(&source);
+        }
+        command.arg("-map").arg("0:a");
+        if let Some(stream) = attached_picture {
+            command.arg("-map").arg(format!("2:{}", stream)).arg("-disposition:v:0").arg("attached_pic");
+        }
+        command.arg("-map_metadata").arg("1");
+        if OGG_EXTENSIONS.contains(&extension.as_str()) {
+            // Ogg keeps its comments on the stream rather than the file.
+            command.arg("-map_metadata:s:a").arg("1:g");
+        }
+        let result = command
+            .arg("-c")
+            .arg("copy")
+            .arg("-y")
+            .arg(&tagged_path)
+            .stderr(Stdio::piped())
+            .output();
+        let _ = std::fs::remove_file(&metadata_path);
+        let result = result.map_err(|e| format!("Failed to execute ffmpeg for tagging. Error: {}", e))?;
+
+        if !result.status.success() {
+            let _ = std::fs::remove_file(&tagged_path);
+            let stderr_str = String::from_utf8_lossy(&result.stderr);
+            return Err(format!("FFmpeg failed to tag '{}'. Status: {}. Stderr:\n{}", output.display(), result.status, stderr_str));
+        }
+        std::fs::rename(&tagged_path, output)
+            .map_err(|e| format!("Failed to replace '{}' with its tagged copy: {}", output.display(), e))
+    }
+
+    fn extract_segment(&self, input: &Path, segment: &Segment, output: &Path) -> Result<(), String> {
+        let status = Command::new("ffmpeg")
+            .args(input_format_args(input))
+            .arg("-i")
+            .arg(input)
+            .arg("-ss")
+            .arg(format!("{}", segment.start))
+            .arg("-t")
+            .arg(format!("{}", segment.duration()))
+            // The same streams as the single pass in `extract_segments`: the audio only.
+            .arg("-map")
+            .arg("0:a")
+            .arg("-c")
+            .arg("copy")
+            .arg("-y")
+            .arg(output)
+            .status()
+            .map_err(|e| format!("Failed to execute ffmpeg for splitting. Error: {}", e))?;
+
+        if !status.success() {
+            return Err(format!("FFmpeg splitting failed for part {}. Status: {}", segment.number, status));
+        }
+        Ok(())
+    }
+
+    fn extract_segments(&self, input: &Path, outputs: &[SplitOutput], progress: &dyn Fn(f64), warning: &dyn Fn(&str)) -> Result<(), String> {
+        // One pass with the segment muxer instead of one process (and seek) per segment.
+        if let Some(segment_times) = segment_times(outputs) {
+            match extract_in_one_pass(input, outputs, &segment_times, progress) {
+                Ok(()) => return Ok(()),
+                Err(e) => warning(&format!("Single-pass splitting failed, splitting part by part instead. {}", e)),
+            }
+        }
+
+        for output in outputs {
+            self.extract_segment(input, &output.segment, &output.path)?;
+            progress(output.segment.end);
+        }
+        Ok(())
+    }
+
+    fn extract_segment_accurate(&self, input: &Path, segment: &Segment, output: &Path) -> Result<(), String> {
+        // Seeking before `-i` decodes from the preceding packet and drops the samples
+        // before the start, and re-encoding lets the segment end on any sample.
+        let status = Command::new("ffmpeg")
+            .args(input_format_args(input))
+            .arg("-ss")
+            .arg(format!("{}", segment.start))
+            .arg("-i")
+            .arg(input)
+            .arg("-t")
+            .arg(format!("{}", segment.duration()))
+            .arg("-map")
+            .arg("0:a")
+            .arg("-y")
+            .arg(output)
+            .status()
+            .map_err(|e| format!("Failed to execute ffmpeg for splitting. Error: {}", e))?;
+
+        if !status.success() {
+            return Err(format!("FFmpeg accurate splitting failed for part {}. Status: {}", segment.number, status));
+        }
+        Ok(())
+    }
+
+    fn write_chaptered_m4b(&self, input: &Path, segments: &[Segment], output: &Path) -> Result<(), String> {
+        // The chapters are handed to FFmpeg as a metadata file next to the output.
+        let metadata_path = output.with_extension("ffmetadata.txt");
+        std::fs::write(&metadata_path, ffmetadata_chapters(segments))
+            .map_err(|e| format!("Failed to write chapter metadata '{}': {}", metadata_path.display(), e))?;
+
+        // AAC audio can be remuxed as is, anything else has to be encoded.
+        let input_extension = audio_extension(input).to_lowercase();
+        let audio_codec = if ["m4a", "m4b", "aac", "mp4"].contains(&input_extension.as_str()) { "copy" } else { "aac" };
+
+        let status = Command::new("ffmpeg")
+            .args(input_format_args(input))
+            .arg("-i")
+            .arg(input)
+            .arg("-i")
+            .arg(&metadata_path)
+            .arg("-map")
+            .arg("0:a")
+            .arg("-map_metadata")
+            .arg("0")
+            .arg("-map_chapters")
+            .arg("1")
+            .arg("-c:a")
+            .arg(audio_codec)
+            .arg("-f")
+            .arg("mp4")
+            .arg("-y")
+            .arg(output)
+            .status();
+        let _ = std::fs::remove_file(&metadata_path);
+        let status = status.map_err(|e| format!("Failed to execute ffmpeg for the M4B export. Error: {}", e))?;
+
+        if !status.success() {
+            return Err(format!("FFmpeg failed to write the chaptered M4B file. Status: {}", status));
+        }
+        Ok(())
+    }
+}
+
+// Lists the container format and the streams of `input` with ffprobe, in the form
+// `parse_ffprobe_streams` reads. Returns `None` if ffprobe can't read the input.
+fn probe_stream_listing(input: &Path) -> Result<Option<String>, String> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-show_entries")
+        .arg("format=format_name:stream=codec_type,codec_name:stream_tags=language,title:stream_disposition=default,attached_pic")
+        .arg("-of")
+        .arg("flat")
+        .args(input_format_args(input))
+        .arg(input)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn ffprobe. Please ensure FFprobe is installed and in your PATH. Error: {}", e))?
+        .wait_with_output()
+        .map_err(|e| format!("Failed to wait for ffprobe process: {}", e))?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+// Returns the index of the stream holding the cover art of `source`, if it has one.
+fn find_cover_art_stream(source: &Path) -> Result<Option<usize>, String> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-show_entries")
+        .arg("stream=index:stream_disposition=attached_pic")
+        .arg("-of")
+        .arg("flat")
+        .arg(source)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn ffprobe. Please ensure FFprobe is installed and in your PATH. Error: {}", e))?
+        .wait_with_output()
+        .map_err(|e| format!("Failed to wait for ffprobe process: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!("FFprobe failed to list the streams of '{}'. Status: {}", source.display(), output.status));
+    }
+    Ok(parse_cover_art_stream(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Finds the attached picture in the flat ffprobe listing of `stream=index:stream_disposition=attached_pic`.
+fn parse_cover_art_stream(output: &str) -> Option<usize> {
+    let field = |position: &str, name: &str| {
+        output.lines().find_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            (key == format!("streams.stream.{}.{}", position, name)).then(|| unquote_flat_value(value))
+        })
+    };
+    output.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("streams.stream.")?;
+        let (position, rest) = rest.split_once('.')?;
+        if rest != "disposition.attached_pic=1" {
+            return None;
+        }
+        field(position, "index")?.parse().ok()
+    })
+}
+
+// Reads the picture in stream `stream` of `source` as is.
+fn read_cover_art(source: &Path, stream: usize) -> Result<Vec<u8>, String> {
+    let output = Command::new("ffmpeg")
+        .arg("-v")
+        .arg("error")
+        .arg("-i")
+        .arg(source)
+        .arg("-map")
+        .arg(format!("0:{}", stream))
+        .arg("-c")
+        .arg("copy")
+        .arg("-f")
+        .arg("image2pipe")
+        .arg("-")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg to read the cover art. Error: {}", e))?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        let stderr_str = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("FFmpeg failed to read the cover art of '{}'. Stderr:\n{}", source.display(), stderr_str));
+    }
+    Ok(output.stdout)
+}
+
+// Concat lists are read with the concat demuxer; `-safe 0` allows the absolute paths they contain.
+fn input_format_args(input: &Path) -> &'static [&'static str] {
+    if is_concat_list(input) { &["-f", "concat", "-safe", "0"] } else { &[] }
+}
+
+// The cut times for the segment muxer, or `None` when a single pass doesn't apply: there
+// must be at least two segments, covering the input from the start without gaps and
+// sharing one folder and format, since the muxer names every piece from one pattern.
+fn segment_times(outputs: &[SplitOutput]) -> Option<String> {
+    if outputs.len() < 2 {
+        return None;
+    }
+    let first = &outputs[0];
+    let same_place = |output: &SplitOutput| {
+        output.path.parent() == first.path.parent() && output.path.extension() == first.path.extension()
+    };
+    if first.segment.start.abs() > 1e-6 || !outputs.iter().all(same_place) {
+        return None;
+    }
+    if outputs.windows(2).any(|pair| (pair[1].segment.start - pair[0].segment.end).abs() > 1e-6) {
+        return None;
+    }
+
+    let times: Vec<String> = outputs[..outputs.len() - 1].iter().map(|output| format!("{}", output.segment.end)).collect();
+    Some(times.join(","))
+}
+
+// Cuts all segments with FFmpeg's segment muxer into temporary files next to the
+// outputs, then renames them. Leaves no temporary files behind on failure.
+fn extract_in_one_pass(input: &Path, outputs: &[SplitOutput], segment_times: &str, progress: &dyn Fn(f64)) -> Result<(), String> {
+    // Every pass gets its own temporary names, as several inputs may be split into one folder at once.
+    static PASS_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let pass = PASS_COUNTER.fetch_add(1, Ordering::SeqCst);
+
+    let first_output = &outputs[0].path;
+    let output_dir = first_output.parent().unwrap_or(Path::new("."));
+    let extension = first_output.extension().unwrap_or_default().to_string_lossy();
+    let temporary_prefix = format!(".split-{}-{}", std::process::id(), pass);
+    let temporary_name = |index: usize| -> PathBuf {
+        output_dir.join(format!("{}-{:05}.{}", temporary_prefix, index, extension))
+    };
+    let remove_temporary_files = || {
+        let mut index = 0;
+        while std::fs::remove_file(temporary_name(index)).is_ok() {
+            index += 1;
+        }
+    };
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(input_format_args(input))
+        .arg("-i")
+        .arg(input)
+        .arg("-map")
+        .arg("0:a")
+        .arg("-c")
+        .arg("copy")
+        .arg("-f")
+        .arg("segment")
+        .arg("-segment_times")
+        .arg(segment_times)
+        .arg("-reset_timestamps")
+        .arg("1")
+        .arg("-y")
+        .arg(output_dir.join(format!("{}-%05d.{}", temporary_prefix, extension)));
+    let (status, stderr_str) = run_with_progress(&mut command, progress)
+        .map_err(|e| format!("Failed to execute ffmpeg for splitting. Error: {}", e))?;
+
+    // The muxer skips cut times past the end of the input, so every piece must be there.
+    let piece_count = (0..).take_while(|&index| temporary_name(index).exists()).count();
+    if !status.success() || piece_count != outputs.len() {
+        remove_temporary_files();
+        return Err(format!("FFmpeg wrote {} of {} parts. Status: {}. Stderr:\n{}", piece_count, outputs.len(), status, stderr_str));
+    }
+
+    for (index, output) in outputs.iter().enumerate() {
+        if let Err(e) = std::fs::rename(temporary_name(index), &output.path) {
+            remove_temporary_files();
+            return Err(format!("Failed to move part {} to '{}': {}", output.segment.number, output.path.display(), e));
+        }
+    }
+    Ok(())
+}
+
+// Runs an FFmpeg command that reports its progress on stdout, calling `progress` with the
+// output position in seconds as it advances. Returns the exit status and the log from stderr.
+fn run_with_progress(command: &mut Command, progress: &dyn Fn(f64)) -> std::io::Result<(ExitStatus, String)> {
+    let mut child = command
+        .arg("-progress")
+        .arg("pipe:1")
+        .arg("-nostats")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // The log is drained on another thread so neither pipe can fill up and stall FFmpeg.
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let log_reader = std::thread::spawn(move || {
+        let mut log = Vec::new();
+        let _ = stderr.read_to_end(&mut log);
+        String::from_utf8_lossy(&log).to_string()
+    });
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        if let Some(seconds) = parse_progress_line(&line) {
+            progress(seconds);
+        }
+    }
+
+    let status = child.wait()?;
+    let log = log_reader.join().unwrap_or_default();
+    Ok((status, log))
+}
+
+// Reads the output position from a `key=value` line of FFmpeg's `-progress` report.
+fn parse_progress_line(line: &str) -> Option<f64> {
+    let microseconds = line.trim().strip_prefix("out_time_us=")?.parse::<i64>().ok()?;
+    Some(microseconds.max(0) as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(number: usize, start: f64, end: f64, path: &str) -> SplitOutput {
+        SplitOutput { segment: Segment { number, start, end, title: None }, path: PathBuf::from(path) }
+    }
+
+    #[test]
+    fn contiguous_segments_are_cut_in_one_pass() {
+        let outputs = [output(1, 0.0, 11.0, "out/a_001.mp3"), output(2, 11.0, 32.5, "out/a_002 - Two.mp3"), output(3, 32.5, 40.0, "out/a_003.mp3")];
+        assert_eq!(segment_times(&outputs).as_deref(), Some("11,32.5"));
+        assert_eq!(segment_times(&outputs[..1]), None);
+
+        // A gap (e.g. a skipped tiny segment) or a different folder needs separate cuts.
+        assert_eq!(segment_times(&[output(1, 0.0, 11.0, "out/a_001.mp3"), output(2, 11.5, 40.0, "out/a_002.mp3")]), None);
+        assert_eq!(segment_times(&[output(1, 0.0, 11.0, "out/a_001.mp3"), output(2, 11.0, 40.0, "b/a_002.mp3")]), None);
+        assert_eq!(segment_times(&outputs[1..]), None);
+    }
+
+    #[test]
+    fn finds_the_attached_picture() {
+        let output = "streams.stream.0.index=0\nstreams.stream.0.disposition.attached_pic=0\nstreams.stream.1.index=1\nstreams.stream.1.disposition.attached_pic=1\n";
+        assert_eq!(parse_cover_art_stream(output), Some(1));
+        assert_eq!(parse_cover_art_stream("streams.stream.0.index=0\nstreams.stream.0.disposition.attached_pic=0\n"), None);
+    }
+
+    #[test]
+    fn reads_position_from_progress_report() {
+        assert_eq!(parse_progress_line("out_time_us=83500000"), Some(83.5));
+        assert_eq!(parse_progress_line("out_time_us=N/A"), None);
+        assert_eq!(parse_progress_line("out_time=00:01:23.500000"), None);
+        assert_eq!(parse_progress_line("progress=continue"), None);
+    }
+
+    #[test]
+    fn lines_that_are_not_utf8_dont_end_the_log() {
+        // A Latin-1 tag in the input banner, between two silences.
+        let mut log = b"[silencedetect @ 0x1] silence_start: 1\n[silencedetect @ 0x1] silence_end: 2.5 | silence_duration: 1.5\n".to_vec();
+        log.extend_from_slice(b"    title           : Kapitel f\xfcnf\r\n");
+        log.extend_from_slice(b"[silencedetect @ 0x1] silence_start: 5\nout_time_us=9000000\n");
+        let events: Vec<DetectionEvent> = LogEvents::new(log.as_slice()).collect();
+
+        let silences: Vec<(f64, f64)> = events
+            .iter()
+            .filter_map(|event| match event {
+                DetectionEvent::Silence(silence) => Some((silence.start, silence.end)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(silences, [(1.0, 2.5), (5.0, 9.0)]);
+    }
+}
@@ -0,0 +1,137 @@
+//! Media backends perform the actual audio work: probing, silence detection
+//! and segment extraction.
+
+use std::path::Path;
+
+use crate::chapters::Chapter;
+use crate::silence::Silence;
+use crate::splitter::{Segment, SplitOutput};
+use crate::streams::MediaStreams;
+use crate::tags::Tags;
+
+mod ffmpeg;
+#[cfg(feature = "native")]
+mod native;
+mod silencedetect;
+
+pub use ffmpeg::{DetectionEvents, FfmpegBackend};
+#[cfg(feature = "native")]
+pub use native::NativeBackend;
+
+/// What a running silence detection reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetectionEvent {
+    /// The detection has processed this many seconds of the input.
+    Progress(f64),
+    /// A silence that has just ended.
+    Silence(Silence),
+    /// The detection skipped this many packets of the input it couldn't decode. Reported
+    /// once, when the input is done.
+    SkippedPackets(usize),
+}
+
+/// The operations the splitter needs from a media toolkit.
+///
+/// [`FfmpegBackend`] is the default implementation; other implementations can be
+/// plugged into a [`Splitter`](crate::Splitter) with
+/// [`Splitter::backend`](crate::Splitter::backend).
+pub trait MediaBackend: Send + Sync {
+    /// Returns the total duration of the input in seconds.
+    fn probe_duration(&self, input: &Path) -> Result<f64, String>;
+
+    /// Detects every silence of at least `silence_threshold_seconds` that stays
+    /// below `noise_threshold_db`, with the same semantics as FFmpeg's `silencedetect`.
+    fn detect_silences(
+        &self,
+        input: &Path,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+    ) -> Result<Vec<Silence>, String>;
+
+    /// Like [`detect_silences`](Self::detect_silences), but passes the progress and each
+    /// silence to `on_event` as soon as they are known, while the detection still runs.
+    ///
+    /// The default implementation reports the silences once the detection is done and
+    /// no progress at all.
+    fn detect_silences_streaming(
+        &self,
+        input: &Path,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+        on_event: &mut dyn FnMut(DetectionEvent),
+    ) -> Result<Vec<Silence>, String> {
+        let silences = self.detect_silences(input, silence_threshold_seconds, noise_threshold_db)?;
+        for silence in &silences {
+            on_event(DetectionEvent::Silence(*silence));
+        }
+        Ok(silences)
+    }
+
+    /// Returns the chapter markers embedded in the input, sorted by start time.
+    /// Backends that can't read chapters return an empty list.
+    fn probe_chapters(&self, _input: &Path) -> Result<Vec<Chapter>, String> {
+        Ok(Vec::new())
+    }
+
+    /// Returns the tags of the input and whether it has cover art.
+    /// Backends that can't read tags return none.
+    fn probe_tags(&self, _input: &Path) -> Result<Tags, String> {
+        Ok(Tags::default())
+    }
+
+    /// Checks by content whether `input` holds audio, whatever its extension. Returns the
+    /// extension of a format its audio can be cut into (e.g. `opus` or `m4a`), or `None`
+    /// if it has no audio.
+    fn probe_audio_format(&self, _input: &Path) -> Result<Option<String>, String> {
+        Err("This media backend can't probe files by their content.".to_string())
+    }
+
+    /// Returns the container format of the input and its audio streams, and whether it
+    /// has video.
+    fn probe_streams(&self, _input: &Path) -> Result<MediaStreams, String> {
+        Err("This media backend can't list the streams of a file.".to_string())
+    }
+
+    /// Writes audio stream number `stream` of `input` (counting the audio streams only,
+    /// from 0) to `output` without any video, replacing any existing file. The audio is
+    /// copied as is, or with `encode` converted to the format of `output`.
+    fn extract_audio_stream(&self, _input: &Path, _stream: usize, _output: &Path, _encode: bool) -> Result<(), String> {
+        Err("This media backend can't extract audio streams.".to_string())
+    }
+
+    /// Replaces the tags of `output`, a segment cut from `input`, with `tags`, and gives
+    /// it the cover art of `input` if `tags.cover_art` is set.
+    fn write_tags(&self, _input: &Path, _output: &Path, _tags: &Tags) -> Result<(), String> {
+        Err("This media backend can't write tags.".to_string())
+    }
+
+    /// Writes the time range of `segment` from `input` to `output`, replacing any existing file.
+    fn extract_segment(&self, input: &Path, segment: &Segment, output: &Path) -> Result<(), String>;
+
+    /// Writes the time range of every segment in `outputs` to its path, replacing any
+    /// existing files, and calls `progress` with the position in the input reached so far.
+    /// Problems that don't stop the cut (e.g. falling back to a slower way) are reported
+    /// through `warning`.
+    ///
+    /// The default implementation calls [`extract_segment`](Self::extract_segment) once per
+    /// segment. Backends that can cut all segments in one pass over the input override it.
+    fn extract_segments(&self, input: &Path, outputs: &[SplitOutput], progress: &dyn Fn(f64), _warning: &dyn Fn(&str)) -> Result<(), String> {
+        for output in outputs {
+            self.extract_segment(input, &output.segment, &output.path)?;
+            progress(output.segment.end);
+        }
+        Ok(())
+    }
+
+    /// Like [`extract_segment`](Self::extract_segment), but re-encodes the audio so
+    /// the output starts and ends exactly at the segment boundaries.
+    fn extract_segment_accurate(&self, _input: &Path, _segment: &Segment, _output: &Path) -> Result<(), String> {
+        Err("This media backend can't cut segments accurately.".to_string())
+    }
+
+    /// Writes all of `input` to a single M4B file at `output`, with a chapter marker
+    /// for each segment, replacing any existing file.
+    fn write_chaptered_m4b(&self, _input: &Path, _segments: &[Segment], _output: &Path) -> Result<(), String> {
+        Err("This media backend can't write chaptered M4B files.".to_string())
+    }
+}
@@ -0,0 +1,298 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::{DetectionEvent, FfmpegBackend, MediaBackend};
+use crate::chapters::Chapter;
+use crate::concat::{concat_list_files, is_concat_list};
+use crate::silence::{Silence, SilenceDetector};
+use crate::splitter::{Segment, SplitOutput};
+use crate::streams::MediaStreams;
+use crate::tags::Tags;
+
+/// Backend that decodes audio in-process with Symphonia for probing and silence
+/// detection, so no FFmpeg process is spawned for the analysis.
+///
+/// Concat lists are decoded file by file into one continuous timeline. Reading
+/// chapters and writing output files still need FFmpeg and are delegated to
+/// [`FfmpegBackend`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeBackend;
+
+impl NativeBackend {
+    // Opens the input and returns its format reader together with the first decodable audio track.
+    fn open(input: &Path) -> Result<(Box<dyn FormatReader>, Track), String> {
+        let file = File::open(input).map_err(|e| format!("Failed to open '{}': {}", input.display(), e))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = input.extension().and_then(|s| s.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| format!("Failed to recognize the audio format of '{}': {}", input.display(), e))?;
+
+        let track = Self::audio_track(probed.format.as_ref(), input)?;
+        Ok((probed.format, track))
+    }
+
+    // The first decodable audio track of `format`, which was opened from `input`.
+    fn audio_track(format: &dyn FormatReader, input: &Path) -> Result<Track, String> {
+        format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .cloned()
+            .ok_or_else(|| format!("No decodable audio track found in '{}'.", input.display()))
+    }
+
+    // A decoder for `track` of `input` and the sample rate it decodes at.
+    fn make_decoder(track: &Track, input: &Path) -> Result<(Box<dyn Decoder>, u32), String> {
+        let sample_rate = track.codec_params.sample_rate
+            .ok_or_else(|| format!("Unknown sample rate for '{}'.", input.display()))?;
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| format!("Unsupported codec in '{}': {}", input.display(), e))?;
+        Ok((decoder, sample_rate))
+    }
+
+    // The audio files behind an input: the joined files of a concat list, or the input itself.
+    fn audio_files(input: &Path) -> Result<Vec<PathBuf>, String> {
+        if is_concat_list(input) { concat_list_files(input) } else { Ok(vec![input.to_path_buf()]) }
+    }
+
+    // Decodes every audio file of the input in order and feeds their samples into one silence detector.
+    // `on_event` receives the decoded duration and each silence as decoding goes on.
+    fn decode(
+        input: &Path,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+        on_event: &mut dyn FnMut(DetectionEvent),
+    ) -> Result<SilenceDetector, String> {
+        let mut detector = None;
+        for file in Self::audio_files(input)? {
+            Self::decode_file(&file, &mut detector, silence_threshold_seconds, noise_threshold_db, on_event)?;
+        }
+        detector.ok_or_else(|| format!("No audio found in '{}'.", input.display()))
+    }
+
+    // Decodes the whole audio track of one file, creating the detector on the first file.
+    fn decode_file(
+        input: &Path,
+        detector: &mut Option<SilenceDetector>,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+        on_event: &mut dyn FnMut(DetectionEvent),
+    ) -> Result<(), String> {
+        let (mut format, mut track) = Self::open(input)?;
+        let (mut decoder, sample_rate) = Self::make_decoder(&track, input)?;
+
+        let detector = detector.get_or_insert_with(|| SilenceDetector::new(sample_rate, silence_threshold_seconds, noise_threshold_db));
+        // The detector measures time in sample frames, so joined files must share a sample rate.
+        if detector.sample_rate() != sample_rate {
+            return Err(format!("'{}' has a sample rate of {} Hz, but the files before it have {} Hz.",
+                               input.display(), sample_rate, detector.sample_rate()));
+        }
+        let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+        let mut skipped_packets = 0;
+
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                // The end of the stream is reported as an unexpected EOF.
+                Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                // The track list changed (e.g. at the next stream of a chained Ogg file), so
+                // decoding goes on with a new decoder for the audio track from here on.
+                Err(SymphoniaError::ResetRequired) => {
+                    track = Self::audio_track(format.as_ref(), input)?;
+                    let (new_decoder, new_sample_rate) = Self::make_decoder(&track, input)?;
+                    if new_sample_rate != detector.sample_rate() {
+                        return Err(format!("'{}' changes its sample rate from {} Hz to {} Hz.",
+                                           input.display(), detector.sample_rate(), new_sample_rate));
+                    }
+                    decoder = new_decoder;
+                    sample_buffer = None;
+                    continue;
+                }
+                Err(e) => return Err(format!("Failed to read '{}': {}", input.display(), e)),
+            };
+            if packet.track_id() != track.id {
+                continue;
+            }
+
+            match decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let channels = decoded.spec().channels.count();
+                    let buffer = sample_buffer.get_or_insert_with(|| {
+                        SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec())
+                    });
+                    if buffer.capacity() < decoded.capacity() * channels {
+                        *buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+                    }
+                    buffer.copy_interleaved_ref(decoded);
+                    let known_silences = detector.silences().len();
+                    detector.push_interleaved(buffer.samples(), channels);
+                    for silence in &detector.silences()[known_silences..] {
+                        on_event(DetectionEvent::Silence(*silence));
+                    }
+                    on_event(DetectionEvent::Progress(detector.duration()));
+                }
+                // Corrupt packets are skipped, just like FFmpeg does.
+                Err(SymphoniaError::DecodeError(_)) => skipped_packets += 1,
+                Err(e) => return Err(format!("Failed to decode '{}': {}", input.display(), e)),
+            }
+        }
+
+        if skipped_packets > 0 {
+            on_event(DetectionEvent::SkippedPackets(skipped_packets));
+        }
+        Ok(())
+    }
+}
+
+impl MediaBackend for NativeBackend {
+    fn probe_duration(&self, input: &Path) -> Result<f64, String> {
+        if is_concat_list(input) {
+            return concat_list_files(input)?.iter().map(|file| self.probe_duration(file)).sum();
+        }
+
+        let (_, track) = Self::open(input)?;
+        let params = &track.codec_params;
+        match (params.n_frames, params.sample_rate) {
+            (Some(frames), Some(sample_rate)) if sample_rate > 0 => Ok(frames as f64 / sample_rate as f64),
+            // Some containers don't store the length, so the audio has to be decoded to measure it.
+            _ => Ok(Self::decode(input, f64::INFINITY, -f64::INFINITY, &mut |_| {})?.duration()),
+        }
+    }
+
+    fn detect_silences(
+        &self,
+        input: &Path,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+    ) -> Result<Vec<Silence>, String> {
+        self.detect_silences_streaming(input, silence_threshold_seconds, noise_threshold_db, &mut |_| {})
+    }
+
+    fn detect_silences_streaming(
+        &self,
+        input: &Path,
+        silence_threshold_seconds: f64,
+        noise_threshold_db: f64,
+        on_event: &mut dyn FnMut(DetectionEvent),
+    ) -> Result<Vec<Silence>, String> {
+        let detector = Self::decode(input, silence_threshold_seconds, noise_threshold_db, on_event)?;
+        // A silence running up to the end of the audio is only known once decoding is done.
+        let known_silences = detector.silences().len();
+        let silences = detector.finish();
+        for silence in &silences[known_silences..] {
+            on_event(DetectionEvent::Silence(*silence));
+        }
+        Ok(silences)
+    }
+
+    fn probe_chapters(&self, input: &Path) -> Result<Vec<Chapter>, String> {
+        FfmpegBackend.probe_chapters(input)
+    }
+
+    fn probe_tags(&self, input: &Path) -> Result<Tags, String> {
+        FfmpegBackend.probe_tags(input)
+    }
+
+    fn probe_audio_format(&self, input: &Path) -> Result<Option<String>, String> {
+        FfmpegBackend.probe_audio_format(input)
+    }
+
+    fn probe_streams(&self, input: &Path) -> Result<MediaStreams, String> {
+        FfmpegBackend.probe_streams(input)
+    }
+
+    fn extract_audio_stream(&self, input: &Path, stream: usize, output: &Path, encode: bool) -> Result<(), String> {
+        FfmpegBackend.extract_audio_stream(input, stream, output, encode)
+    }
+
+    fn write_tags(&self, input: &Path, output: &Path, tags: &Tags) -> Result<(), String> {
+        FfmpegBackend.write_tags(input, output, tags)
+    }
+
+    fn extract_segment(&self, input: &Path, segment: &Segment, output: &Path) -> Result<(), String> {
+        FfmpegBackend.extract_segment(input, segment, output)
+    }
+
+    fn extract_segments(&self, input: &Path, outputs: &[SplitOutput], progress: &dyn Fn(f64), warning: &dyn Fn(&str)) -> Result<(), String> {
+        FfmpegBackend.extract_segments(input, outputs, progress, warning)
+    }
+
+    fn extract_segment_accurate(&self, input: &Path, segment: &Segment, output: &Path) -> Result<(), String> {
+        FfmpegBackend.extract_segment_accurate(input, segment, output)
+    }
+
+    fn write_chaptered_m4b(&self, input: &Path, segments: &[Segment], output: &Path) -> Result<(), String> {
+        FfmpegBackend.write_chaptered_m4b(input, segments, output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes a mono 16-bit PCM WAV file from samples in the range -1.0 to 1.0.
+    fn write_wav(path: &Path, sample_rate: u32, samples: &[f32]) {
+        let data_len = (samples.len() * 2) as u32;
+        let mut bytes = Vec::new();
+        bytes.extend(b"RIFF");
+        bytes.extend((36 + data_len).to_le_bytes());
+        bytes.extend(b"WAVEfmt ");
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes()); // PCM
+        bytes.extend(1u16.to_le_bytes()); // mono
+        bytes.extend(sample_rate.to_le_bytes());
+        bytes.extend((sample_rate * 2).to_le_bytes());
+        bytes.extend(2u16.to_le_bytes());
+        bytes.extend(16u16.to_le_bytes());
+        bytes.extend(b"data");
+        bytes.extend(data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend(((sample * i16::MAX as f32) as i16).to_le_bytes());
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn detects_silences_in_a_wav_file() {
+        let sample_rate = 8000;
+        let tone = |seconds: usize| -> Vec<f32> {
+            (0..seconds * sample_rate).map(|i| (i as f32 * 0.3).sin() * 0.5).collect()
+        };
+        let mut samples = tone(2);
+        samples.extend(vec![0.0; 3 * sample_rate]);
+        samples.extend(tone(1));
+
+        let path = std::env::temp_dir().join(format!("audiobook-splitter-native-{}.wav", std::process::id()));
+        write_wav(&path, sample_rate as u32, &samples);
+
+        let duration = NativeBackend.probe_duration(&path).unwrap();
+        let mut skipped_packets = 0;
+        let silences = NativeBackend.detect_silences_streaming(&path, 2.0, -40.0, &mut |event| {
+            if let DetectionEvent::SkippedPackets(packets) = event {
+                skipped_packets += packets;
+            }
+        }).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(duration, 6.0);
+        assert_eq!(silences.len(), 1);
+        assert!((silences[0].start - 2.0).abs() < 0.01, "start was {}", silences[0].start);
+        assert!((silences[0].end - 5.0).abs() < 0.01, "end was {}", silences[0].end);
+        assert_eq!(skipped_packets, 0);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::silence::Silence;
+
+/// Turns the log lines of FFmpeg's `silencedetect` filter into silences, one line at a time,
+/// so the log never has to be held in memory.
+///
+/// FFmpeg prints timestamps with `%.6g`, so besides decimals they can be integers (`0`),
+/// negative (`-0.0235` for inputs with encoder priming) or in scientific notation
+/// (`1e-05`). Silences are paired per filter instance and channel, so the logs of several
+/// streams or of `mono` mode don't get mixed up.
+pub(crate) struct SilenceParser {
+    open: Vec<(String, f64)>, // Source and start of the silences that haven't ended yet.
+}
+
+impl SilenceParser {
+    pub(crate) fn new() -> Self {
+        SilenceParser { open: Vec::new() }
+    }
+
+    /// Reads one log line and returns the silence it completes, if any.
+    pub(crate) fn push_line(&mut self, line: &str) -> Result<Option<Silence>, String> {
+        let Some((source, fields)) = parse_silencedetect_line(line) else {
+            return Ok(None);
+        };
+        let field = |key: &str| fields.iter().find(|(name, _)| *name == key).map(|(_, value)| *value);
+
+        if let Some(start) = field("silence_start") {
+            let start = parse_timestamp(start).map_err(|e| format!("Failed to parse silence start time: {}", e))?;
+            self.open.push((source, start));
+        } else if let Some(end) = field("silence_end") {
+            let end = parse_timestamp(end).map_err(|e| format!("Failed to parse silence end time: {}", e))?;
+            // An end without a start (e.g. of a log that doesn't begin at the top) has
+            // nothing to pair with.
+            let Some(index) = self.open.iter().rposition(|(open_source, _)| *open_source == source) else {
+                return Ok(None);
+            };
+            let (_, start) = self.open.remove(index);
+            // A silence can't start before the audio does, and then only its part
+            // after the start of the audio counts.
+            if start < 0.0 {
+                return Ok(Some(Silence { start: 0.0, end, duration: end }));
+            }
+            let duration = match field("silence_duration") {
+                Some(duration) => parse_timestamp(duration).map_err(|e| format!("Failed to parse silence duration: {}", e))?,
+                None => end - start,
+            };
+            return Ok(Some(Silence { start, end, duration }));
+        }
+        Ok(None)
+    }
+
+    /// Ends the log. Silences that were still open when the audio ended at `end_of_audio`
+    /// seconds run up to there; older FFmpeg versions never report their end.
+    pub(crate) fn finish(&mut self, end_of_audio: f64) -> Vec<Silence> {
+        self.open
+            .drain(..)
+            .map(|(_, start)| start.max(0.0))
+            .filter(|&start| end_of_audio > start)
+            .map(|start| Silence { start, end: end_of_audio, duration: end_of_audio - start })
+            .collect()
+    }
+}
+
+// Splits a `silencedetect` log line into its source (the filter instance plus the channel
+// in `mono` mode) and its `key: value` fields, e.g.
+// `[silencedetect @ 0x5581] channel: 1 | silence_end: 14.5 | silence_duration: 2.155`.
+fn parse_silencedetect_line(line: &str) -> Option<(String, Vec<(&str, &str)>)> {
+    let line = line.trim();
+    let (instance, body) = match line.find("[silencedetect") {
+        Some(position) => {
+            let rest = &line[position..];
+            let close = rest.find(']')?;
+            (&rest[..=close], &rest[close + 1..])
+        }
+        // Without a log prefix only the fields themselves can identify the line.
+        None if line.starts_with("silence_") || line.starts_with("channel:") => ("", line),
+        None => return None,
+    };
+
+    let fields: Vec<(&str, &str)> = body
+        .split('|')
+        .filter_map(|field| field.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+    if !fields.iter().any(|(key, _)| key.starts_with("silence_")) {
+        return None;
+    }
+
+    let channel = fields.iter().find(|(key, _)| *key == "channel").map_or("", |(_, value)| value);
+    Some((format!("{}#{}", instance, channel), fields))
+}
+
+// Parses a timestamp as printed by FFmpeg.
+fn parse_timestamp(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() => Ok(seconds),
+        _ => Err(format!("'{}' is not a valid timestamp", value)),
+    }
+}
+
+// Extracts the silence regions reported by the 'silencedetect' filter from a complete log.
+#[cfg(test)]
+fn parse_silencedetect_output(stderr_str: &str, end_of_audio: f64) -> Result<Vec<Silence>, String> {
+    let mut parser = SilenceParser::new();
+    let mut detected_silences = Vec::new();
+    for line in stderr_str.lines() {
+        detected_silences.extend(parser.push_line(line)?);
+    }
+    detected_silences.extend(parser.finish(end_of_audio));
+    Ok(detected_silences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn silence(start: f64, end: f64, duration: f64) -> Silence {
+        Silence { start, end, duration }
+    }
+
+    #[test]
+    fn parses_silence_pairs() {
+        let log = "\
+[silencedetect @ 0x5581] silence_start: 12.345
+size=N/A time=00:00:20.00 bitrate=N/A speed= 100x
+[silencedetect @ 0x5581] silence_end: 14.5 | silence_duration: 2.155
+[silencedetect @ 0x5581] silence_start: 30.25
+[silencedetect @ 0x5581] silence_end: 33.75 | silence_duration: 3.5
+";
+        let silences = parse_silencedetect_output(log, 40.0).unwrap();
+        assert_eq!(silences, vec![silence(12.345, 14.5, 2.155), silence(30.25, 33.75, 3.5)]);
+    }
+
+    #[test]
+    fn silences_complete_line_by_line() {
+        let mut parser = SilenceParser::new();
+        assert_eq!(parser.push_line("[silencedetect @ 0x1] silence_start: 1.5").unwrap(), None);
+        assert_eq!(parser.push_line("out_time_us=2000000").unwrap(), None);
+        assert_eq!(parser.push_line("[silencedetect @ 0x1] silence_end: 4.0 | silence_duration: 2.5").unwrap(),
+                   Some(silence(1.5, 4.0, 2.5)));
+    }
+
+    #[test]
+    fn accepts_every_number_format() {
+        let log = "\
+[silencedetect @ 0x1] silence_start: 0
+[silencedetect @ 0x1] silence_end: 3 | silence_duration: 3
+[silencedetect @ 0x1] silence_start: -0.0235
+[silencedetect @ 0x1] silence_end: 1e-05 | silence_duration: 0.02351
+[silencedetect @ 0x1] silence_start: 1.23457e+06
+[silencedetect @ 0x1] silence_end: 1.23458e+06 | silence_duration: 10
+";
+        let silences = parse_silencedetect_output(log, 2e6).unwrap();
+        assert_eq!(silences, vec![
+            silence(0.0, 3.0, 3.0),
+            silence(0.0, 1e-05, 1e-05),
+            silence(1_234_570.0, 1_234_580.0, 10.0),
+        ]);
+        assert!(parse_silencedetect_output("[silencedetect @ 0x1] silence_start: nan\n", 1.0).is_err());
+    }
+
+    // The `testdata` logs follow the output of the FFmpeg versions they are named after but
+    // haven't been captured yet, as their first line says. `testdata/capture.sh` records
+    // them (with the version and command used) from the FFmpeg in the `PATH`; the expected
+    // silences below then have to follow the new logs.
+
+    #[test]
+    fn ffmpeg_6_log_with_progress_report() {
+        // `ffmpeg -hide_banner -nostats -loglevel info -progress pipe:2 -af silencedetect=n=-40dB:d=2`
+        // on a stereo MP3 with encoder priming; FFmpeg 6 reports the silence at the end itself.
+        let log = include_str!("testdata/silencedetect-ffmpeg6.log");
+        let silences = parse_silencedetect_output(log, 0.0).unwrap();
+        assert_eq!(silences, vec![
+            silence(0.0, 2.50009, 2.50009),
+            silence(61.6327, 64.4082, 2.77551),
+            silence(118.26, 120.816, 2.55592),
+        ]);
+    }
+
+    #[test]
+    fn ffmpeg_4_log_leaves_the_last_silence_open() {
+        // FFmpeg 4 never ends a silence that runs up to the end of the file.
+        let log = include_str!("testdata/silencedetect-ffmpeg4.log");
+        let silences = parse_silencedetect_output(log, 1800.0).unwrap();
+        assert_eq!(silences, vec![
+            silence(905.112, 908.016, 2.90404),
+            silence(1796.5, 1800.0, 3.5),
+        ]);
+    }
+
+    #[test]
+    fn channels_and_filter_instances_are_paired_separately() {
+        // Two audio streams through `-filter_complex`, the second one in `mono` mode.
+        let log = include_str!("testdata/silencedetect-streams.log");
+        let silences = parse_silencedetect_output(log, 100.0).unwrap();
+        assert_eq!(silences, vec![
+            silence(10.0, 12.5, 2.5),
+            silence(11.0, 14.0, 3.0),
+            silence(21.0, 22.5, 1.5),
+            silence(20.0, 23.0, 3.0),
+            silence(11.5, 100.0, 88.5),
+        ]);
+    }
+}
@@ -0,0 +1,64 @@
+#!/bin/sh
+# Captures the silencedetect logs the parser tests read, with the FFmpeg in the PATH.
+#
+#   sh capture.sh ffmpeg6    # writes silencedetect-ffmpeg6.log
+#   sh capture.sh ffmpeg4    # writes silencedetect-ffmpeg4.log (run with an FFmpeg 4 build)
+#   sh capture.sh streams    # writes silencedetect-streams.log
+#
+# The test audio is generated with FFmpeg itself: a 440 Hz tone with silent stretches,
+# under the file names, tags and stream languages the logs show. Each log starts with `#`
+# lines recording the FFmpeg version and the command, which the parser skips. Update the
+# expected silences in silencedetect.rs after a new capture.
+set -eu
+testdata=$(cd "$(dirname "$0")" && pwd)
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+# The inputs are named without a folder, as in the banner of the logs.
+cd "$work"
+
+# A tone that is silent where `$1` (an aevalsrc condition on `t`) holds.
+tone() {
+    echo "if($1,0,0.5*sin(2*PI*440*t))"
+}
+
+capture() {
+    log=$1
+    shift
+    {
+        echo "# $(ffmpeg -hide_banner -version | head -n 1)"
+        echo "# ffmpeg $*"
+        ffmpeg "$@" 2>&1 >/dev/null
+    } >"$testdata/$log"
+}
+
+case "${1:-}" in
+    ffmpeg6)
+        # A stereo MP3 with encoder priming, silent at the start, in the middle and up to the end.
+        ffmpeg -v error -f lavfi -i "aevalsrc=$(tone 'lt(t,2.5)+between(t,61.6,64.4)+gt(t,118.3)')|$(tone 'lt(t,2.5)+between(t,61.6,64.4)+gt(t,118.3)'):s=44100:d=120.8" \
+            -c:a libmp3lame -b:a 128k chapter.mp3
+        capture silencedetect-ffmpeg6.log -i chapter.mp3 -hide_banner -nostats -loglevel info -progress pipe:2 \
+            -af silencedetect=n=-40dB:d=2 -f null -
+        ;;
+    ffmpeg4)
+        # Half an hour of FLAC with a pause in the middle and a silence up to the end.
+        ffmpeg -v error -f lavfi -i "aevalsrc=$(tone 'between(t,905.1,908.0)+gt(t,1796.5)'):s=44100:d=1800" \
+            -ac 2 -c:a flac -metadata title="Part 1" part1.flac
+        capture silencedetect-ffmpeg4.log -i part1.flac -hide_banner -nostats -loglevel info -progress pipe:2 \
+            -af silencedetect=n=-40dB:d=2 -f null -
+        ;;
+    streams)
+        # Two Opus streams in English and German, the second one stereo with channels that fall
+        # silent at different times.
+        ffmpeg -v error -f lavfi -i "aevalsrc=$(tone 'between(t,10,12.5)+between(t,20,23)'):s=48000:d=100" \
+            -f lavfi -i "aevalsrc=$(tone 'between(t,11,14)+between(t,21,22.5)')|$(tone 'gt(t,11.5)'):s=48000:d=100" \
+            -map 0 -map 1 -c:a libopus -metadata:s:a:0 language=eng -metadata:s:a:1 language=deu \
+            -disposition:a:0 default lecture.mkv
+        capture silencedetect-streams.log -i lecture.mkv -hide_banner -nostats -loglevel info \
+            -filter_complex "[0:a:0]silencedetect=n=-40dB:d=1[a0];[0:a:1]silencedetect=n=-40dB:d=1:mono=1[a1]" \
+            -map "[a0]" -map "[a1]" -f null -
+        ;;
+    *)
+        echo "Usage: sh capture.sh ffmpeg6|ffmpeg4|streams" >&2
+        exit 2
+        ;;
+esac
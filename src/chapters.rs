@@ -0,0 +1,147 @@
+use crate::splitter::Segment;
+
+/// A chapter marker embedded in the input container, with timestamps in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub start: f64,            // The starting timestamp of the chapter in seconds.
+    pub end: f64,              // The ending timestamp of the chapter in seconds.
+    pub title: Option<String>, // The chapter title, if the container has one.
+}
+
+/// Parses the output of `ffprobe -show_chapters -of flat`, which looks like
+/// `chapters.chapter.0.start_time="0.000000"` and `chapters.chapter.0.tags.title="Intro"`.
+pub fn parse_ffprobe_chapters(output: &str) -> Result<Vec<Chapter>, String> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+
+    for line in output.lines() {
+        let Some(rest) = line.trim().strip_prefix("chapters.chapter.") else { continue };
+        let Some((key, value)) = rest.split_once('=') else { continue };
+        let Some((index, field)) = key.split_once('.') else { continue };
+        let index = index.parse::<usize>().map_err(|e| format!("Invalid chapter index '{}': {}", index, e))?;
+
+        while chapters.len() <= index {
+            chapters.push(Chapter { start: 0.0, end: 0.0, title: None });
+        }
+        let chapter = &mut chapters[index];
+        let value = unquote_flat_value(value);
+
+        match field {
+            "start_time" => chapter.start = value.parse::<f64>().map_err(|e| format!("Failed to parse chapter start time: {}", e))?,
+            "end_time" => chapter.end = value.parse::<f64>().map_err(|e| format!("Failed to parse chapter end time: {}", e))?,
+            // Tag keys keep the case used by the container, e.g. "TITLE" in Matroska.
+            _ if field.eq_ignore_ascii_case("tags.title") => {
+                let title = value.trim();
+                if !title.is_empty() {
+                    chapter.title = Some(title.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    Ok(chapters)
+}
+
+// Removes the quotes and backslash escapes ffprobe's flat writer puts around string values.
+pub(crate) fn unquote_flat_value(value: &str) -> String {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                unescaped.push(next);
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+/// Builds an FFmpeg metadata file (`;FFMETADATA1`) with one chapter per segment.
+/// Segments without a title are named "Chapter N".
+pub fn ffmetadata_chapters(segments: &[Segment]) -> String {
+    let mut metadata = String::from(";FFMETADATA1\n");
+    for (i, segment) in segments.iter().enumerate() {
+        let title = segment.title.clone().unwrap_or_else(|| format!("Chapter {}", i + 1));
+        metadata.push_str("[CHAPTER]\nTIMEBASE=1/1000\n");
+        metadata.push_str(&format!("START={}\n", (segment.start * 1000.0).round() as u64));
+        metadata.push_str(&format!("END={}\n", (segment.end * 1000.0).round() as u64));
+        metadata.push_str(&format!("title={}\n", escape_ffmetadata_value(&title)));
+    }
+    metadata
+}
+
+// Escapes the characters that have a special meaning in FFmpeg metadata files.
+pub(crate) fn escape_ffmetadata_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_flat_chapter_output() {
+        let output = r#"chapters.chapter.0.id=0
+chapters.chapter.0.time_base="1/1000"
+chapters.chapter.0.start=0
+chapters.chapter.0.start_time="0.000000"
+chapters.chapter.0.end=62500
+chapters.chapter.0.end_time="62.500000"
+chapters.chapter.0.tags.title="Opening \"Credits\""
+chapters.chapter.1.id=1
+chapters.chapter.1.start_time="62.500000"
+chapters.chapter.1.end_time="120.000000"
+chapters.chapter.1.tags.TITLE="Chapter 1"
+chapters.chapter.2.start_time="120.000000"
+chapters.chapter.2.end_time="180.250000"
+"#;
+        let chapters = parse_ffprobe_chapters(output).unwrap();
+        assert_eq!(chapters, vec![
+            Chapter { start: 0.0, end: 62.5, title: Some("Opening \"Credits\"".to_string()) },
+            Chapter { start: 62.5, end: 120.0, title: Some("Chapter 1".to_string()) },
+            Chapter { start: 120.0, end: 180.25, title: None },
+        ]);
+    }
+
+    #[test]
+    fn writes_ffmetadata_chapters() {
+        let segments = [
+            Segment { number: 1, start: 0.0, end: 62.5, title: Some("Intro; Part=1".to_string()) },
+            Segment { number: 2, start: 62.5, end: 120.0004, title: None },
+        ];
+        assert_eq!(ffmetadata_chapters(&segments), "\
+;FFMETADATA1
+[CHAPTER]
+TIMEBASE=1/1000
+START=0
+END=62500
+title=Intro\\; Part\\=1
+[CHAPTER]
+TIMEBASE=1/1000
+START=62500
+END=120000
+title=Chapter 2
+");
+    }
+
+    #[test]
+    fn no_chapters_yields_empty_list() {
+        assert!(parse_ffprobe_chapters("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+/// The extension of the lists that join several audio files into one continuous input.
+pub const CONCAT_LIST_EXTENSION: &str = "ffconcat";
+
+/// Whether `path` is a concat list rather than an audio file.
+pub fn is_concat_list(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(CONCAT_LIST_EXTENSION))
+}
+
+/// Writes an FFmpeg concat list that plays `files` back to back as one timeline.
+///
+/// Each entry is a file path and its duration in seconds. The durations let FFmpeg
+/// place every file on the joined timeline without probing them all up front.
+pub fn write_concat_list(files: &[(PathBuf, f64)]) -> String {
+    let mut list = String::from("ffconcat version 1.0\n");
+    for (path, duration) in files {
+        list.push_str(&format!("file {}\n", quote_concat_string(&path.to_string_lossy())));
+        list.push_str(&format!("duration {}\n", duration));
+    }
+    list
+}
+
+/// Reads the file entries of an FFmpeg concat list in order.
+/// Relative paths are resolved against `base_dir`, the directory of the list.
+pub fn parse_concat_list(contents: &str, base_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        let (directive, arguments) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if directive == "file" {
+            let file = unquote_concat_string(arguments.trim());
+            if file.is_empty() {
+                return Err(format!("Invalid file entry on line {} of the concat list.", line_number + 1));
+            }
+            files.push(base_dir.join(file));
+        }
+        // `ffconcat version`, `duration`, comments and other directives don't name files.
+    }
+
+    if files.is_empty() {
+        return Err("The concat list has no file entries.".to_string());
+    }
+    Ok(files)
+}
+
+/// Reads the audio files joined by the concat list at `path`.
+pub fn concat_list_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read concat list '{}': {}", path.display(), e))?;
+    parse_concat_list(&contents, path.parent().unwrap_or(Path::new(".")))
+}
+
+// The audio file behind `path`: the first joined file for concat lists.
+pub(crate) fn first_audio_file(path: &Path) -> PathBuf {
+    let audio_path = if is_concat_list(path) {
+        concat_list_files(path).ok().and_then(|files| files.into_iter().next())
+    } else {
+        None
+    };
+    audio_path.unwrap_or_else(|| path.to_path_buf())
+}
+
+// The extension of the audio behind `path`: that of the first joined file for concat lists.
+pub(crate) fn audio_extension(path: &Path) -> String {
+    first_audio_file(path).extension().unwrap_or_default().to_string_lossy().to_string()
+}
+
+// Concat list strings follow shell quoting: single quotes can't be escaped inside
+// quotes, so a quote is written as `'\''` (close, escaped quote, reopen).
+fn quote_concat_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Undoes the shell-style quoting and backslash escapes of a concat list string.
+fn unquote_concat_string(arguments: &str) -> String {
+    let mut value = String::new();
+    let mut in_quotes = false;
+    let mut chars = arguments.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => in_quotes = !in_quotes,
+            '\\' if !in_quotes => value.extend(chars.next()),
+            '#' if !in_quotes => break,
+            c if c.is_whitespace() && !in_quotes => break,
+            c => value.push(c),
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_list_round_trips() {
+        let files = vec![
+            (PathBuf::from("/books/Disc 1.mp3"), 1800.5),
+            (PathBuf::from("/books/Author's Note.mp3"), 60.0),
+        ];
+        let list = write_concat_list(&files);
+        assert_eq!(list, "\
+ffconcat version 1.0
+file '/books/Disc 1.mp3'
+duration 1800.5
+file '/books/Author'\\''s Note.mp3'
+duration 60
+");
+        let parsed = parse_concat_list(&list, Path::new("/elsewhere")).unwrap();
+        assert_eq!(parsed, vec![PathBuf::from("/books/Disc 1.mp3"), PathBuf::from("/books/Author's Note.mp3")]);
+    }
+
+    #[test]
+    fn relative_entries_resolve_against_the_list() {
+        let parsed = parse_concat_list("# my book\nfile part\\ 1.flac\nfile 'part 2.flac'\n", Path::new("lists")).unwrap();
+        assert_eq!(parsed, vec![PathBuf::from("lists/part 1.flac"), PathBuf::from("lists/part 2.flac")]);
+        assert!(parse_concat_list("ffconcat version 1.0\n", Path::new(".")).is_err());
+    }
+}
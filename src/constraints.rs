@@ -0,0 +1,148 @@
+use crate::silence::Silence;
+
+/// Merges segments shorter than `min_segment_seconds` into a neighbour.
+///
+/// `split_points` are segment end times finishing with the total duration. The
+/// shortest offending segment is merged first, always into its shorter neighbour,
+/// until every segment is long enough or only one segment is left.
+pub fn merge_short_segments(split_points: &[f64], min_segment_seconds: f64) -> Vec<f64> {
+    let mut split_points = split_points.to_vec();
+
+    while split_points.len() > 1 {
+        let lengths = segment_lengths(&split_points);
+        let Some((shortest, &length)) = lengths
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(b.1))
+        else {
+            break;
+        };
+        if length >= min_segment_seconds {
+            break;
+        }
+
+        // Removing split point `i` joins segments `i` and `i + 1`.
+        let merge_with_previous = match (shortest.checked_sub(1).map(|i| lengths[i]), lengths.get(shortest + 1)) {
+            (Some(previous), Some(&next)) => previous <= next,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        let removed = if merge_with_previous { shortest - 1 } else { shortest };
+        split_points.remove(removed);
+    }
+
+    split_points
+}
+
+/// Subdivides segments longer than `max_segment_seconds` at the best silence inside them.
+///
+/// The best silence is the longest candidate that leaves both parts at least
+/// `min_segment_seconds` long; ties go to the one closest to the middle of the segment.
+/// Parts that are still too long are subdivided again. Segments without a usable
+/// silence are left as they are.
+pub fn subdivide_long_segments(
+    split_points: &[f64],
+    max_segment_seconds: f64,
+    min_segment_seconds: f64,
+    candidates: &[Silence],
+) -> Vec<f64> {
+    let mut result = Vec::new();
+    let mut segment_start = 0.0;
+    for &segment_end in split_points {
+        subdivide(segment_start, segment_end, max_segment_seconds, min_segment_seconds, candidates, &mut result);
+        result.push(segment_end);
+        segment_start = segment_end;
+    }
+    result
+}
+
+// Pushes the split points needed inside `start..end` (but not `end` itself) in order.
+fn subdivide(start: f64, end: f64, max: f64, min: f64, candidates: &[Silence], result: &mut Vec<f64>) {
+    if end - start <= max {
+        return;
+    }
+
+    let middle = (start + end) / 2.0;
+    let best = candidates
+        .iter()
+        .map(|silence| (silence.midpoint(), silence.duration))
+        .filter(|&(point, _)| point - start >= min && end - point >= min && point > start && point < end)
+        .max_by(|a, b| {
+            a.1.total_cmp(&b.1)
+                .then_with(|| (b.0 - middle).abs().total_cmp(&(a.0 - middle).abs()))
+        });
+
+    if let Some((point, _)) = best {
+        subdivide(start, point, max, min, candidates, result);
+        result.push(point);
+        subdivide(point, end, max, min, candidates, result);
+    }
+}
+
+/// Picks the silences that `split_points` are placed at (see [`compute_split_points`](crate::compute_split_points))
+/// from `silences`, once each and in order, e.g. after merging dropped some split points
+/// and subdividing added some at secondary silences.
+pub fn silences_at_split_points(split_points: &[f64], silences: &[Silence]) -> Vec<Silence> {
+    let mut result: Vec<Silence> = split_points
+        .iter()
+        .filter_map(|&point| silences.iter().find(|silence| (silence.midpoint() - point).abs() < 1e-9).copied())
+        .collect();
+    result.dedup();
+    result
+}
+
+// The length of each segment described by a list of split points.
+fn segment_lengths(split_points: &[f64]) -> Vec<f64> {
+    let mut previous = 0.0;
+    split_points
+        .iter()
+        .map(|&point| {
+            let length = point - previous;
+            previous = point;
+            length
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn silence(start: f64, duration: f64) -> Silence {
+        Silence { start, end: start + duration, duration }
+    }
+
+    #[test]
+    fn short_segments_merge_into_shorter_neighbour() {
+        // Segments: 0-3 (3s), 3-50 (47s), 50-52 (2s), 52-60 (8s), 60-100 (40s)
+        let merged = merge_short_segments(&[3.0, 50.0, 52.0, 60.0, 100.0], 10.0);
+        // 50-52 joins 52-60, then 0-3 joins 3-50 (its only neighbour).
+        assert_eq!(merged, vec![50.0, 60.0, 100.0]);
+    }
+
+    #[test]
+    fn merging_stops_at_a_single_segment() {
+        assert_eq!(merge_short_segments(&[1.0, 2.0, 3.0], 10.0), vec![3.0]);
+    }
+
+    #[test]
+    fn long_segments_split_at_longest_secondary_silence() {
+        let candidates = [silence(20.0, 0.5), silence(40.0, 1.5), silence(70.0, 1.5), silence(98.0, 3.0)];
+        // 0-100 is too long; the 3s silence would leave a 0.5s tail, so the 1.5s silences
+        // win and the one closer to the middle (40.75) is taken first. The remaining
+        // 40.75-100 is still too long and is split at 70.75.
+        let result = subdivide_long_segments(&[100.0], 45.0, 5.0, &candidates);
+        assert_eq!(result, vec![40.75, 70.75, 100.0]);
+    }
+
+    #[test]
+    fn silences_follow_the_split_points() {
+        let silences = [silence(2.0, 2.0), silence(40.0, 2.0), silence(70.0, 1.0), silence(40.0, 2.0)];
+        assert_eq!(silences_at_split_points(&[41.0, 70.5, 120.0], &silences), vec![silence(40.0, 2.0), silence(70.0, 1.0)]);
+    }
+
+    #[test]
+    fn segments_without_candidates_are_kept() {
+        assert_eq!(subdivide_long_segments(&[10.0, 200.0], 60.0, 0.0, &[]), vec![10.0, 200.0]);
+    }
+}
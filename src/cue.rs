@@ -0,0 +1,235 @@
+use crate::chapters::Chapter;
+use crate::splitter::Segment;
+
+// CUE sheets count time in frames of 1/75 second (the CD sector rate).
+const CUE_FRAMES_PER_SECOND: f64 = 75.0;
+
+/// A parsed CUE sheet describing the tracks of a single audio file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>, // The album title, if the sheet has one.
+    pub file: String,          // The audio file the tracks refer to, as written in the sheet.
+    pub tracks: Vec<CueTrack>,
+}
+
+/// One TRACK entry of a CUE sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub start: f64, // The INDEX 01 position of the track in seconds.
+}
+
+impl CueSheet {
+    /// Converts the tracks into chapters, each ending where the next one starts.
+    pub fn chapters(&self, total_duration: f64) -> Vec<Chapter> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| Chapter {
+                start: track.start,
+                end: self.tracks.get(i + 1).map_or(total_duration, |next| next.start),
+                title: track.title.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Formats seconds as a CUE timestamp `MM:SS:FF`, rounded to the nearest frame.
+pub fn format_cue_time(seconds: f64) -> String {
+    let total_frames = (seconds.max(0.0) * CUE_FRAMES_PER_SECOND).round() as u64;
+    let frames = total_frames % 75;
+    let total_seconds = total_frames / 75;
+    format!("{:02}:{:02}:{:02}", total_seconds / 60, total_seconds % 60, frames)
+}
+
+/// Parses a CUE timestamp `MM:SS:FF` into seconds.
+pub fn parse_cue_time(time: &str) -> Result<f64, String> {
+    let parts: Vec<&str> = time.trim().split(':').collect();
+    let [minutes, seconds, frames] = parts.as_slice() else {
+        return Err(format!("Invalid CUE timestamp '{}'. Expected MM:SS:FF.", time));
+    };
+    let parse = |value: &str| value.parse::<u64>().map_err(|_| format!("Invalid CUE timestamp '{}'.", time));
+    let (minutes, seconds, frames) = (parse(minutes)?, parse(seconds)?, parse(frames)?);
+    if seconds >= 60 || frames >= 75 {
+        return Err(format!("Invalid CUE timestamp '{}'.", time));
+    }
+    Ok((minutes * 60 + seconds) as f64 + frames as f64 / CUE_FRAMES_PER_SECOND)
+}
+
+/// Writes a CUE sheet with one track per segment for the audio file `file`.
+/// Segments with a title get a TITLE entry.
+pub fn write_cue_sheet(file: &str, title: Option<&str>, segments: &[Segment]) -> String {
+    let mut sheet = String::new();
+    if let Some(title) = title {
+        sheet.push_str(&format!("TITLE {}\n", quote_cue_string(title)));
+    }
+    sheet.push_str(&format!("FILE {} {}\n", quote_cue_string(file), cue_file_type(file)));
+    for (i, segment) in segments.iter().enumerate() {
+        sheet.push_str(&format!("  TRACK {:02} AUDIO\n", i + 1));
+        if let Some(title) = &segment.title {
+            sheet.push_str(&format!("    TITLE {}\n", quote_cue_string(title)));
+        }
+        sheet.push_str(&format!("    INDEX 01 {}\n", format_cue_time(segment.start)));
+    }
+    sheet
+}
+
+/// Parses a CUE sheet that refers to a single audio file.
+pub fn parse_cue_sheet(contents: &str) -> Result<CueSheet, String> {
+    let mut title = None;
+    let mut file: Option<String> = None;
+    let mut tracks: Vec<CueTrack> = Vec::new();
+    let mut track_has_index = true;
+
+    // Some tools write a byte order mark at the start of the sheet.
+    for (line_number, line) in contents.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line = line.trim();
+        let (command, arguments) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arguments = arguments.trim();
+
+        match command.to_uppercase().as_str() {
+            "FILE" => {
+                if file.is_some() {
+                    return Err("CUE sheets with more than one FILE are not supported.".to_string());
+                }
+                file = Some(unquote_cue_string(arguments));
+            }
+            "TITLE" => {
+                let value = unquote_cue_string(arguments);
+                match tracks.last_mut() {
+                    Some(track) => track.title = Some(value),
+                    None => title = Some(value),
+                }
+            }
+            "TRACK" => {
+                if !track_has_index {
+                    return Err(format!("Track {} has no INDEX 01 entry.", tracks.last().map_or(0, |t| t.number)));
+                }
+                let number = arguments
+                    .split_whitespace()
+                    .next()
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .ok_or_else(|| format!("Invalid TRACK entry on line {}.", line_number + 1))?;
+                tracks.push(CueTrack { number, title: None, start: 0.0 });
+                track_has_index = false;
+            }
+            "INDEX" => {
+                let mut parts = arguments.split_whitespace();
+                let (Some(index), Some(time)) = (parts.next(), parts.next()) else {
+                    return Err(format!("Invalid INDEX entry on line {}.", line_number + 1));
+                };
+                // Only INDEX 01 marks the start of a track; INDEX 00 is the pregap.
+                if index.parse::<u32>() == Ok(1) {
+                    let track = tracks
+                        .last_mut()
+                        .ok_or_else(|| format!("INDEX outside of a TRACK on line {}.", line_number + 1))?;
+                    track.start = parse_cue_time(time)?;
+                    track_has_index = true;
+                }
+            }
+            _ => {} // REM, PERFORMER, FLAGS, ... don't affect the split points.
+        }
+    }
+
+    if !track_has_index {
+        return Err(format!("Track {} has no INDEX 01 entry.", tracks.last().map_or(0, |t| t.number)));
+    }
+    let file = file.ok_or_else(|| "The CUE sheet has no FILE entry.".to_string())?;
+    if tracks.is_empty() {
+        return Err("The CUE sheet has no tracks.".to_string());
+    }
+    Ok(CueSheet { title, file, tracks })
+}
+
+// The FILE type keyword for an audio file, based on its extension.
+fn cue_file_type(file: &str) -> &'static str {
+    let extension = file.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "mp3" => "MP3",
+        "aif" | "aiff" => "AIFF",
+        _ => "WAVE", // Also used by convention for FLAC and other formats.
+    }
+}
+
+// CUE strings are wrapped in double quotes, which therefore can't appear inside them.
+fn quote_cue_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "'"))
+}
+
+// Reads a possibly quoted string argument, dropping anything after the closing quote.
+fn unquote_cue_string(arguments: &str) -> String {
+    match arguments.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or_default().to_string(),
+        None => arguments.split_whitespace().next().unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_frame_accurate_timestamps() {
+        assert_eq!(format_cue_time(0.0), "00:00:00");
+        assert_eq!(format_cue_time(62.5), "01:02:38");
+        assert_eq!(format_cue_time(7322.04), "122:02:03");
+        assert_eq!(parse_cue_time("01:02:38").unwrap(), 62.0 + 38.0 / 75.0);
+        assert!(parse_cue_time("01:60:00").is_err());
+        assert!(parse_cue_time("01:02").is_err());
+    }
+
+    #[test]
+    fn written_sheet_round_trips() {
+        let segments = [
+            Segment { number: 1, start: 0.0, end: 62.5, title: Some("Prologue".to_string()) },
+            Segment { number: 2, start: 62.5, end: 130.0, title: None },
+        ];
+        let sheet = write_cue_sheet("book.flac", Some("The Book"), &segments);
+        assert_eq!(sheet, "\
+TITLE \"The Book\"
+FILE \"book.flac\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"Prologue\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 01:02:38
+");
+
+        let parsed = parse_cue_sheet(&sheet).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("The Book"));
+        assert_eq!(parsed.file, "book.flac");
+        assert_eq!(parsed.tracks.len(), 2);
+        assert_eq!(parsed.tracks[0].title.as_deref(), Some("Prologue"));
+        assert!((parsed.tracks[1].start - 62.5).abs() < 1.0 / 75.0);
+    }
+
+    #[test]
+    fn parses_sheets_from_other_tools() {
+        let sheet = "\u{feff}REM GENRE Audiobook
+PERFORMER \"Someone\"
+FILE \"disc 1.wav\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"One\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Two\"
+    INDEX 00 04:58:00
+    INDEX 01 05:00:00
+";
+        let parsed = parse_cue_sheet(sheet).unwrap();
+        assert_eq!(parsed.file, "disc 1.wav");
+        assert_eq!(parsed.chapters(600.0), vec![
+            Chapter { start: 0.0, end: 300.0, title: Some("One".to_string()) },
+            Chapter { start: 300.0, end: 600.0, title: Some("Two".to_string()) },
+        ]);
+    }
+
+    #[test]
+    fn rejects_incomplete_sheets() {
+        assert!(parse_cue_sheet("TRACK 01 AUDIO\nINDEX 01 00:00:00\n").is_err()); // no FILE
+        assert!(parse_cue_sheet("FILE \"a.wav\" WAVE\n").is_err()); // no tracks
+        assert!(parse_cue_sheet("FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\n").is_err()); // no INDEX
+        assert!(parse_cue_sheet("FILE \"a.wav\" WAVE\nFILE \"b.wav\" WAVE\n").is_err());
+    }
+}
@@ -1,14 +1,51 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
+//! Splits long audio files (audiobooks, podcasts, ...) at the midpoint of detected silences.
+//!
+//! By default the heavy lifting is done by FFmpeg and FFprobe, which must be installed
+//! and accessible in the `PATH`. Other tools can be plugged in by implementing
+//! [`MediaBackend`]. With the `native` cargo feature, `NativeBackend` detects
+//! silences without FFmpeg by decoding the audio in-process.
+//!
+//! ```no_run
+//! use audiobook_splitter_rust::Splitter;
+//!
+//! let splitter = Splitter::new("output_splits")
+//!     .silence_threshold_seconds(2.0)
+//!     .noise_threshold_db(-40.0)
+//!     .on_status(|message| println!("{}", message));
+//!
+//! let written = splitter.split_file("book.mp3").unwrap();
+//! println!("Wrote {} files", written.len());
+//! ```
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub mod backend;
+mod chapters;
+mod concat;
+mod constraints;
+mod cue;
+mod output;
+mod plan;
+mod progress;
+mod scan;
+mod search;
+mod silence;
+mod splitter;
+mod streams;
+mod tags;
+mod template;
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+pub use output::{find_split_files, format_directory, format_file_name, get_next_file_index, names_identify_input, validate_directory_template, validate_name_template, DEFAULT_NAME_TEMPLATE, DEFAULT_TITLED_NAME_TEMPLATE};
+pub use plan::SplitPlan;
+pub use scan::{collect_audio_files, natural_cmp, scan_audio_files, sort_by_tags, validate_glob, ScanOptions, SymlinkPolicy, AUDIO_EXTENSIONS};
+pub use backend::{DetectionEvent, DetectionEvents, FfmpegBackend, MediaBackend};
+pub use chapters::{ffmetadata_chapters, parse_ffprobe_chapters, Chapter};
+pub use concat::{concat_list_files, is_concat_list, parse_concat_list, write_concat_list, CONCAT_LIST_EXTENSION};
+pub use constraints::{merge_short_segments, silences_at_split_points, subdivide_long_segments};
+pub use cue::{format_cue_time, parse_cue_sheet, parse_cue_time, write_cue_sheet, CueSheet, CueTrack};
+#[cfg(feature = "native")]
+pub use backend::NativeBackend;
+pub use progress::{Progress, ProgressStage};
+pub use search::{choose_silence_threshold, ThresholdSearch};
+pub use silence::{compute_split_points, Silence, SilenceDetector};
+pub use splitter::{Analysis, CollisionPolicy, CutMode, OutputMode, Segment, SplitOutput, Splitter};
+pub use streams::{parse_ffprobe_streams, AudioStream, AudioStreamChoice, MediaStreams};
+pub use tags::{format_segment_title, parse_ffprobe_tags, validate_title_template, Tags, DEFAULT_TITLE_TEMPLATE};
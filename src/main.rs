@@ -1,17 +1,738 @@
 // Import necessary modules from the standard library and external crates.
-use std::io::{self, Write}; // For input/output operations (reading user input, printing to console).
-use std::process::{Command, Stdio}; // For running external commands (FFmpeg, FFprobe).
+use std::collections::BTreeMap; // For printing the results of parallel jobs in input order.
+use std::io::{self, IsTerminal, Write}; // For input/output operations (reading user input, printing to console).
+use std::process::ExitCode; // For reporting the outcome of the command-line mode.
 use std::path::{Path, PathBuf}; // For working with file paths.
-use regex::Regex; // For parsing FFmpeg's silence detection output.
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering}; // For handing out inputs to the worker threads.
+use std::sync::{mpsc, Arc, Mutex}; // For collecting the messages of the worker threads.
+use std::time::Instant; // For timing the whole batch.
+use audiobook_splitter_rust::{scan_audio_files, sort_by_tags, validate_directory_template, validate_glob, validate_name_template, validate_title_template, Analysis, AudioStreamChoice, CollisionPolicy, CutMode, FfmpegBackend, MediaBackend, OutputMode, Progress, ScanOptions, SplitPlan, Splitter, SymlinkPolicy, AUDIO_EXTENSIONS, DEFAULT_NAME_TEMPLATE, DEFAULT_TITLE_TEMPLATE}; // The splitting library.
 
-// Define a struct to hold the details of a detected silence region.
-struct Silence {
-    start: f64,    // The starting timestamp of the silence in seconds.
-    end: f64,      // The ending timestamp of the silence in seconds.
-    duration: f64, // The duration of the silence in seconds.
+// Default analysis settings used by the command-line mode when no flag overrides them.
+const DEFAULT_SILENCE_THRESHOLD_SECONDS: f64 = Splitter::DEFAULT_SILENCE_THRESHOLD_SECONDS;
+const DEFAULT_NOISE_THRESHOLD_DB: f64 = Splitter::DEFAULT_NOISE_THRESHOLD_DB;
+
+// Exit codes returned by the command-line mode so scripts can react to failures.
+const EXIT_PROCESSING_FAILED: u8 = 1; // At least one file failed to be analyzed or split.
+const EXIT_USAGE: u8 = 2;             // Invalid arguments or input/output paths.
+const EXIT_NO_INPUT: u8 = 3;          // No supported audio files were found.
+
+// Whether the last console line is a progress line that the next message has to end first.
+static PROGRESS_LINE_OPEN: AtomicBool = AtomicBool::new(false);
+
+// Settings collected from the command-line arguments.
+struct CliOptions {
+    inputs: Vec<PathBuf>,                   // Audio files and/or folders to process.
+    output_dir: PathBuf,                    // Base directory that receives the split files.
+    silence_threshold_seconds: f64,         // Minimum silence length in seconds.
+    noise_threshold_db: f64,                // Noise threshold in dB.
+    min_segment_seconds: Option<f64>,       // Segments shorter than this are merged.
+    max_segment_seconds: Option<f64>,       // Segments longer than this are subdivided.
+    secondary_silence_seconds: Option<f64>, // Minimum silence length for subdividing.
+    dry_run: bool,                          // Only report the detected segments, don't split.
+    backend: BackendChoice,                 // Which media backend performs the analysis.
+    use_chapters: bool,                     // Split on embedded chapters when the input has them.
+    output_mode: OutputMode,                // Separate files, a chaptered M4B, a CUE sheet or a split plan.
+    target_segments: Option<usize>,         // Search for thresholds that give this many segments.
+    continuous: bool,                       // Treat each folder as one continuous book.
+    scan: ScanOptions,                      // Which files of a folder are processed.
+    order_by_tags: bool,                    // Order the files of a folder by their disc and track tags.
+    audio_stream: Option<AudioStreamChoice>, // The audio stream to split in inputs with several.
+    output_format: Option<String>,          // The extension of the format the separate files are written in.
+    cut_mode: CutMode,                      // Stream copy or sample-accurate re-encoding.
+    jobs: usize,                            // How many inputs are processed at the same time.
+    name_template: Option<String>,          // Template for the names of the split files.
+    directory_template: Option<String>,     // Template for each input's subdirectory of the output directory.
+    collision_policy: CollisionPolicy,      // What happens to the files of a previous run.
+    tag_segments: bool,                     // Give each split file its own title and track number.
+    title_template: String,                 // Template for the title tag of each split file.
+}
+
+// The media backends selectable with `--backend`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BackendChoice {
+    Ffmpeg,
+    #[cfg(feature = "native")]
+    Native,
+}
+
+// What the parsed command line asks us to do.
+enum CliCommand {
+    Run(Box<CliOptions>),
+    Help,
+    Version,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Without any arguments we fall back to the interactive wizard.
+    if args.is_empty() {
+        run_interactive();
+        return ExitCode::SUCCESS;
+    }
+
+    match parse_args(&args) {
+        Ok(CliCommand::Help) => {
+            print_usage();
+            ExitCode::SUCCESS
+        }
+        Ok(CliCommand::Version) => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Ok(CliCommand::Run(options)) => run_non_interactive(&options),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("Run with --help for usage information.");
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
+
+// Prints the command-line help text.
+fn print_usage() {
+    println!("Usage: {} [OPTIONS] [INPUT]...", env!("CARGO_PKG_NAME"));
+    println!();
+    println!("Splits audio files at the midpoint of detected silences.");
+    println!("Run without any arguments to start the interactive wizard.");
+    println!();
+    println!("Arguments:");
+    println!("  [INPUT]...                 Audio files, folders of audio files, CUE sheets or split plans to process");
+    println!();
+    println!("Options:");
+    println!("  -i, --input <PATH>         Audio file, folder, CUE sheet or split plan to process (can be repeated)");
+    println!("  -o, --output <DIR>         Base output directory, created if missing (required unless --dry-run)");
+    println!("  -d, --min-silence <SECS>   Minimum silence length in seconds [default: {}]", DEFAULT_SILENCE_THRESHOLD_SECONDS);
+    println!("  -n, --noise-db <DB>        Noise threshold in dB [default: {}]", DEFAULT_NOISE_THRESHOLD_DB);
+    println!("      --min-segment <SECS>   Merge segments shorter than this into a neighbour");
+    println!("      --max-segment <SECS>   Subdivide segments longer than this at shorter silences");
+    println!("      --secondary-silence <SECS>");
+    println!("                             Minimum silence length for subdividing [default: min-silence / 4]");
+    println!("      --target-segments <N>  Search for the silence and noise thresholds that give N segments");
+    println!("      --continuous           Treat each folder as one continuous book split across its files");
+    println!("  -r, --recursive            Also process the files in subfolders, mirroring the folders in the output");
+    println!("      --include <GLOB>       Only process the files matching GLOB, e.g. \"*.mp3\" or \"**/Disc */*\" (can be repeated)");
+    println!("      --exclude <GLOB>       Leave out the files and folders matching GLOB, e.g. \"Extras\" (can be repeated)");
+    println!("      --symlinks <POLICY>    Symbolic links: skip, files (follow links to files only) or follow [default: files]");
+    println!("      --hidden               Also process hidden files and folders");
+    println!("      --formats <LIST>       Extensions of the audio files in a folder, e.g. \"mp3,m4b\", or \"+dts,ac3\" to add");
+    println!("                             to the defaults [default: {}]", AUDIO_EXTENSIONS.join(","));
+    println!("      --probe                Check files with other extensions with FFprobe and process those with audio");
+    println!("      --order <ORDER>        Order of the files in a folder: name (numbers by value) or tags (disc and");
+    println!("                             track number) [default: name]");
+    println!("      --audio-stream <N|LANG>");
+    println!("                             Audio stream to split in videos and other files with several, by number");
+    println!("                             (from 1) or language tag, e.g. \"eng\" [default: the file's default stream]");
+    println!("      --output-format <EXT>  Write the split files in this format, e.g. mp3 or m4a [default: the format");
+    println!("                             of the input's audio]");
+    println!("      --chapters             Split on embedded chapters, falling back to silence detection");
+    println!("      --m4b                  Write one M4B file with a chapter at each split point");
+    println!("      --cue                  Write a CUE sheet with a track at each split point instead of cutting");
+    println!("      --plan                 Write an editable JSON split plan instead of cutting");
+    println!("      --accurate             Re-encode segments so they start and end exactly at the split points");
+    println!("      --name-template <TEMPLATE>");
+    println!("                             Name of each split file, using {{stem}}, {{index}}, {{total}}, {{start}}, {{end}},");
+    println!("                             {{title}}, {{album}} and {{artist}}, e.g. {{index:03}} to zero-pad");
+    println!("                             [default: {}, plus \" - {{title}}\" for titled parts]", DEFAULT_NAME_TEMPLATE);
+    println!("      --subdir <TEMPLATE>    Put the split files of each input into a subdirectory named with {{stem}},");
+    println!("                             {{album}} and {{artist}}, e.g. \"{{artist}}/{{album}}\"");
+    println!("      --on-collision <POLICY>");
+    println!("                             When files of a previous run exist: fail, overwrite, skip (write only");
+    println!("                             missing files) or continue (number after them) [default: fail]");
+    println!("      --title-template <TEMPLATE>");
+    println!("                             Title tag of each split file, using {{title}}, {{index}}, {{total}}, {{album}},");
+    println!("                             {{artist}} and {{stem}} [default: {}]", DEFAULT_TITLE_TEMPLATE);
+    println!("      --no-tags              Keep the tags of the input on the split files as they are");
+    println!("  -j, --jobs <N>             Process up to N inputs at the same time [default: 1]");
+    println!("      --dry-run              Detect silences and report segments without splitting");
+    println!("      --backend <NAME>       Analysis backend: ffmpeg or native [default: ffmpeg]");
+    println!("  -h, --help                 Print this help text");
+    println!("  -V, --version              Print version information");
+    println!();
+    println!("Exit codes:");
+    println!("  0  All files were processed successfully");
+    println!("  {}  At least one file failed to be analyzed or split", EXIT_PROCESSING_FAILED);
+    println!("  {}  Invalid arguments or paths", EXIT_USAGE);
+    println!("  {}  No supported audio files were found", EXIT_NO_INPUT);
+}
+
+// Parses the command-line arguments (without the program name).
+// Option values may be given as separate arguments or as `--flag=value`.
+fn parse_args(args: &[String]) -> Result<CliCommand, String> {
+    let mut inputs: Vec<PathBuf> = Vec::new();
+    let mut output_dir: Option<PathBuf> = None;
+    let mut silence_threshold_seconds = DEFAULT_SILENCE_THRESHOLD_SECONDS;
+    let mut noise_threshold_db = DEFAULT_NOISE_THRESHOLD_DB;
+    let mut min_segment_seconds = None;
+    let mut max_segment_seconds = None;
+    let mut secondary_silence_seconds = None;
+    let mut dry_run = false;
+    let mut backend = BackendChoice::Ffmpeg;
+    let mut use_chapters = false;
+    let mut output_mode = OutputMode::SeparateFiles;
+    let mut target_segments = None;
+    let mut continuous = false;
+    let mut scan = ScanOptions::default();
+    let mut order_by_tags = false;
+    let mut audio_stream = None;
+    let mut output_format = None;
+    let mut cut_mode = CutMode::Copy;
+    let mut jobs = 1;
+    let mut name_template = None;
+    let mut directory_template = None;
+    let mut collision_policy = CollisionPolicy::Fail;
+    let mut tag_segments = true;
+    let mut title_template = DEFAULT_TITLE_TEMPLATE.to_string();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        // Split `--flag=value` into its two halves.
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+
+        // Values are taken verbatim so negative numbers like `-40` are accepted.
+        let mut take_value = || -> Result<String, String> {
+            match inline_value.clone() {
+                Some(value) => Ok(value),
+                None => iter.next().cloned().ok_or_else(|| format!("Missing value for '{}'.", flag)),
+            }
+        };
+
+        match flag {
+            "-h" | "--help" => return Ok(CliCommand::Help),
+            "-V" | "--version" => return Ok(CliCommand::Version),
+            "-i" | "--input" => inputs.push(PathBuf::from(take_value()?)),
+            "-o" | "--output" => output_dir = Some(PathBuf::from(take_value()?)),
+            "-d" | "--min-silence" => {
+                let value = take_value()?;
+                silence_threshold_seconds = match value.parse::<f64>() {
+                    Ok(t) if t > 0.0 => t,
+                    _ => return Err(format!("Invalid minimum silence length '{}'. Please enter a positive number.", value)),
+                };
+            }
+            "-n" | "--noise-db" => {
+                let value = take_value()?;
+                noise_threshold_db = value
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid noise threshold '{}'. Please enter a number (e.g., -40.0).", value))?;
+            }
+            "--min-segment" => min_segment_seconds = Some(parse_seconds(flag, &take_value()?)?),
+            "--max-segment" => max_segment_seconds = Some(parse_seconds(flag, &take_value()?)?),
+            "--secondary-silence" => secondary_silence_seconds = Some(parse_seconds(flag, &take_value()?)?),
+            "--target-segments" => {
+                let value = take_value()?;
+                target_segments = match value.parse::<usize>() {
+                    Ok(count) if count > 0 => Some(count),
+                    _ => return Err(format!("Invalid segment count '{}'. Please enter a positive whole number.", value)),
+                };
+            }
+            "--continuous" => continuous = true,
+            "-r" | "--recursive" => scan.recursive = true,
+            "--include" => {
+                let pattern = take_value()?;
+                validate_glob(&pattern)?;
+                scan.include.push(pattern);
+            }
+            "--exclude" => {
+                let pattern = take_value()?;
+                validate_glob(&pattern)?;
+                scan.exclude.push(pattern);
+            }
+            "--symlinks" => {
+                let value = take_value()?;
+                scan.symlinks = match value.to_lowercase().as_str() {
+                    "skip" => SymlinkPolicy::Skip,
+                    "files" => SymlinkPolicy::Files,
+                    "follow" => SymlinkPolicy::Follow,
+                    _ => return Err(format!("Unknown symlink policy '{}'. Please use 'skip', 'files' or 'follow'.", value)),
+                };
+            }
+            "--hidden" => scan.hidden = true,
+            "--formats" => {
+                let value = take_value()?;
+                // A leading `+` adds to the default formats instead of replacing them.
+                let (mut extensions, list) = match value.strip_prefix('+') {
+                    Some(list) => (AUDIO_EXTENSIONS.iter().map(|extension| extension.to_string()).collect(), list),
+                    None => (Vec::new(), value.as_str()),
+                };
+                extensions.extend(list.split(',').map(|extension| extension.trim().trim_start_matches('.').to_lowercase()).filter(|extension| !extension.is_empty()));
+                if extensions.is_empty() {
+                    return Err(format!("Invalid format list '{}'. Please enter extensions like 'mp3,m4b,opus'.", value));
+                }
+                scan.extensions = extensions;
+            }
+            "--probe" => scan.probe = true,
+            "--order" => {
+                let value = take_value()?;
+                order_by_tags = match value.to_lowercase().as_str() {
+                    "name" => false,
+                    "tags" => true,
+                    _ => return Err(format!("Unknown file order '{}'. Please use 'name' or 'tags'.", value)),
+                };
+            }
+            "--audio-stream" => audio_stream = Some(AudioStreamChoice::parse(&take_value()?)?),
+            "--output-format" => {
+                let value = take_value()?;
+                let extension = value.trim().trim_start_matches('.').to_lowercase();
+                if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(format!("Invalid output format '{}'. Please enter an extension like 'mp3' or 'm4a'.", value));
+                }
+                output_format = Some(extension);
+            }
+            "--accurate" => cut_mode = CutMode::Accurate,
+            "-j" | "--jobs" => {
+                let value = take_value()?;
+                jobs = match value.parse::<usize>() {
+                    Ok(count) if count > 0 => count,
+                    _ => return Err(format!("Invalid number of jobs '{}'. Please enter a positive whole number.", value)),
+                };
+            }
+            "--name-template" => {
+                let template = take_value()?;
+                validate_name_template(&template)?;
+                name_template = Some(template);
+            }
+            "--subdir" => {
+                let template = take_value()?;
+                validate_directory_template(&template)?;
+                directory_template = Some(template);
+            }
+            "--on-collision" => {
+                let value = take_value()?;
+                collision_policy = match value.to_lowercase().as_str() {
+                    "fail" => CollisionPolicy::Fail,
+                    "overwrite" => CollisionPolicy::Overwrite,
+                    "skip" => CollisionPolicy::Skip,
+                    "continue" => CollisionPolicy::Continue,
+                    _ => return Err(format!("Unknown collision policy '{}'. Please use 'fail', 'overwrite', 'skip' or 'continue'.", value)),
+                };
+            }
+            "--title-template" => {
+                title_template = take_value()?;
+                validate_title_template(&title_template)?;
+            }
+            "--no-tags" => tag_segments = false,
+            "--dry-run" => dry_run = true,
+            "--chapters" => use_chapters = true,
+            "--m4b" => output_mode = OutputMode::ChapteredM4b,
+            "--cue" => output_mode = OutputMode::CueSheet,
+            "--plan" => output_mode = OutputMode::Plan,
+            "--backend" => {
+                let value = take_value()?;
+                backend = match value.to_lowercase().as_str() {
+                    "ffmpeg" => BackendChoice::Ffmpeg,
+                    #[cfg(feature = "native")]
+                    "native" => BackendChoice::Native,
+                    #[cfg(not(feature = "native"))]
+                    "native" => return Err("The native backend is not available in this build (enable the 'native' cargo feature).".to_string()),
+                    _ => return Err(format!("Unknown backend '{}'. Please use 'ffmpeg' or 'native'.", value)),
+                };
+            }
+            _ if flag.starts_with('-') && flag.len() > 1 => return Err(format!("Unknown option '{}'.", flag)),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("No input file or folder given.".to_string());
+    }
+    if let (Some(min), Some(max)) = (min_segment_seconds, max_segment_seconds)
+        && min >= max
+    {
+        return Err(format!("The minimum segment length ({}s) must be smaller than the maximum ({}s).", min, max));
+    }
+    if target_segments.is_some() && (min_segment_seconds.is_some() || max_segment_seconds.is_some()) {
+        return Err("--target-segments can't be combined with --min-segment or --max-segment.".to_string());
+    }
+
+    // A dry run never writes anything, so it doesn't need an output directory.
+    let output_dir = match output_dir {
+        Some(dir) => dir,
+        None if dry_run => PathBuf::from("."),
+        None => return Err("No output directory given (use --output <DIR>).".to_string()),
+    };
+
+    Ok(CliCommand::Run(Box::new(CliOptions {
+        inputs,
+        output_dir,
+        silence_threshold_seconds,
+        noise_threshold_db,
+        min_segment_seconds,
+        max_segment_seconds,
+        secondary_silence_seconds,
+        dry_run,
+        backend,
+        use_chapters,
+        output_mode,
+        target_segments,
+        continuous,
+        scan,
+        order_by_tags,
+        audio_stream,
+        output_format,
+        cut_mode,
+        jobs,
+        name_template,
+        directory_template,
+        collision_policy,
+        tag_segments,
+        title_template,
+    })))
+}
+
+// Parses a positive number of seconds given for `flag`.
+fn parse_seconds(flag: &str, value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 => Ok(seconds),
+        _ => Err(format!("Invalid value '{}' for '{}'. Please enter a positive number of seconds.", value, flag)),
+    }
+}
+
+// Runs the splitter from command-line options without asking any questions.
+fn run_non_interactive(options: &CliOptions) -> ExitCode {
+    // Expand folders into the audio files they contain. In continuous mode folders
+    // are kept as they are and joined into one book when their turn comes.
+    // Recursive scans keep the files of each folder apart by mirroring its tree.
+    let mut input_paths: Vec<PathBuf> = Vec::new();
+    let mut mirrored_folders: Vec<&Path> = Vec::new();
+    for input in &options.inputs {
+        if input.is_file() || (options.continuous && input.is_dir()) {
+            input_paths.push(input.clone());
+        } else if input.is_dir() {
+            println!("Status: Scanning folder '{}' for audio files...", input.display());
+            match folder_files(input, &options.scan, options.order_by_tags, &|message| eprintln!("Warning: {}", message)) {
+                Ok(files) => {
+                    println!("Status: Found {} audio files in the folder.", files.len());
+                    input_paths.extend(files);
+                    if options.scan.recursive {
+                        mirrored_folders.push(input);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            }
+        } else {
+            eprintln!("Error: Input '{}' is neither a file nor a directory.", input.display());
+            return ExitCode::from(EXIT_USAGE);
+        }
+    }
+
+    if input_paths.is_empty() {
+        eprintln!("Error: No supported audio files found.");
+        return ExitCode::from(EXIT_NO_INPUT);
+    }
+
+    if !options.dry_run {
+        if options.output_dir.exists() && !options.output_dir.is_dir() {
+            eprintln!("Error: Output path '{}' is not a directory.", options.output_dir.display());
+            return ExitCode::from(EXIT_USAGE);
+        }
+        if let Err(e) = std::fs::create_dir_all(&options.output_dir) {
+            eprintln!("Failed to create directory '{}': {}", options.output_dir.display(), e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    }
+
+    let splitter = console_splitter(&options.output_dir, options.silence_threshold_seconds, options.noise_threshold_db);
+    let mut splitter = splitter.use_chapters(options.use_chapters).output_mode(options.output_mode).cut_mode(options.cut_mode)
+        .tag_segments(options.tag_segments).title_template(options.title_template.clone()).collision_policy(options.collision_policy);
+    if let Some(template) = &options.name_template {
+        splitter = splitter.name_template(template.clone());
+    }
+    if let Some(template) = &options.directory_template {
+        splitter = splitter.directory_template(template.clone());
+    }
+    if let Some(choice) = &options.audio_stream {
+        splitter = splitter.audio_stream(choice.clone());
+    }
+    if let Some(extension) = &options.output_format {
+        splitter = splitter.output_format(extension.clone());
+    }
+    for folder in mirrored_folders {
+        splitter = splitter.mirror_input_tree(folder);
+    }
+    if let Some(seconds) = options.min_segment_seconds {
+        splitter = splitter.min_segment_seconds(seconds);
+    }
+    if let Some(seconds) = options.max_segment_seconds {
+        splitter = splitter.max_segment_seconds(seconds);
+    }
+    if let Some(seconds) = options.secondary_silence_seconds {
+        splitter = splitter.secondary_silence_threshold_seconds(seconds);
+    }
+    let splitter = match options.backend {
+        BackendChoice::Ffmpeg => splitter,
+        #[cfg(feature = "native")]
+        BackendChoice::Native => splitter.backend(audiobook_splitter_rust::NativeBackend),
+    };
+    let failures = process_batch(&splitter, &input_paths, options.jobs, |splitter, audio_file_path, log| {
+        if audio_file_path.is_dir() {
+            // CUE sheets and plans refer to the list, so it is kept for them.
+            let keep_list = !options.dry_run && matches!(options.output_mode, OutputMode::CueSheet | OutputMode::Plan);
+            let book = join_folder(splitter, audio_file_path, &options.scan, options.order_by_tags, options.dry_run, keep_list,
+                                   &|message| log.info(&format!("  Warning: {}", message)))?;
+            process_input(splitter, &book.list_path, options.dry_run, options.target_segments, log)
+        } else {
+            process_input(splitter, audio_file_path, options.dry_run, options.target_segments, log)
+        }
+    });
+
+    if failures > 0 {
+        return ExitCode::from(EXIT_PROCESSING_FAILED);
+    }
+    ExitCode::SUCCESS
+}
+
+// Where the messages about one input go. With a single job they are printed right
+// away; parallel jobs buffer them so each input's output is printed in one piece.
+#[derive(Clone)]
+struct FileLog {
+    buffer: Option<Arc<Mutex<Vec<LogLine>>>>,
+}
+
+// A buffered message for stdout or stderr.
+enum LogLine {
+    Info(String),
+    Error(String),
+}
+
+impl FileLog {
+    fn console() -> Self {
+        FileLog { buffer: None }
+    }
+
+    fn buffered() -> Self {
+        FileLog { buffer: Some(Arc::default()) }
+    }
+
+    fn info(&self, message: &str) {
+        match &self.buffer {
+            Some(buffer) => buffer.lock().unwrap().push(LogLine::Info(message.to_string())),
+            None => {
+                end_progress_line();
+                println!("{}", message);
+            }
+        }
+    }
+
+    fn error(&self, message: &str) {
+        match &self.buffer {
+            Some(buffer) => buffer.lock().unwrap().push(LogLine::Error(message.to_string())),
+            None => {
+                end_progress_line();
+                eprintln!("{}", message);
+            }
+        }
+    }
+
+    // Prints and clears the buffered messages.
+    fn flush(&self) {
+        if let Some(buffer) = &self.buffer {
+            for line in buffer.lock().unwrap().drain(..) {
+                match line {
+                    LogLine::Info(message) => println!("{}", message),
+                    LogLine::Error(message) => eprintln!("{}", message),
+                }
+            }
+        }
+    }
+}
+
+// Processes `inputs` with `process`, running up to `jobs` of them at the same time.
+// Each input's messages are printed in input order once it and every input before it
+// are done, followed by a summary. Returns the number of inputs that failed.
+fn process_batch<F>(splitter: &Splitter, inputs: &[PathBuf], jobs: usize, process: F) -> usize
+where
+    F: Fn(&Splitter, &Path, &FileLog) -> Result<(), String> + Sync,
+{
+    let started = Instant::now();
+    let jobs = jobs.clamp(1, inputs.len().max(1));
+    let next_input = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut failed: Vec<(&Path, String)> = Vec::new();
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next_input, process) = (&next_input, &process);
+            scope.spawn(move || {
+                loop {
+                    let index = next_input.fetch_add(1, Ordering::SeqCst);
+                    let Some(input) = inputs.get(index) else { break };
+                    let log = if jobs > 1 { FileLog::buffered() } else { FileLog::console() };
+                    log.info(&format!("\n--- Processing: {} ---", input.display()));
+
+                    // The splitter's status messages belong to this input's log too. Live progress
+                    // lines would interleave, so they are only shown for a single job.
+                    let status_log = log.clone();
+                    let mut input_splitter = splitter.clone().on_status(move |message| status_log.info(&format!("  {}", message)));
+                    if jobs > 1 {
+                        input_splitter = input_splitter.on_progress(|_| {});
+                    }
+                    let result = process(&input_splitter, input, &log);
+                    match &result {
+                        Ok(()) => log.info(&format!("Successfully completed processing for {}.", input.display())),
+                        Err(e) => log.error(&format!("An error occurred during processing {}: {}", input.display(), e)),
+                    }
+                    if sender.send((index, log, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Inputs finish in any order, but are reported in the order they were given.
+        let mut finished = BTreeMap::new();
+        let mut next_to_print = 0;
+        for (index, log, result) in receiver {
+            finished.insert(index, (log, result));
+            while let Some((log, result)) = finished.remove(&next_to_print) {
+                log.flush();
+                if let Err(e) = result {
+                    failed.push((inputs[next_to_print].as_path(), e));
+                }
+                next_to_print += 1;
+            }
+        }
+    });
+
+    end_progress_line();
+    println!("\nSummary: {} of {} inputs processed successfully in {:.1}s{}.",
+             inputs.len() - failed.len(), inputs.len(), started.elapsed().as_secs_f64(),
+             if jobs > 1 { format!(" ({} jobs)", jobs) } else { String::new() });
+    for (input, e) in &failed {
+        eprintln!("  Failed: {}: {}", input.display(), e);
+    }
+    failed.len()
 }
 
-fn main() {
+// A folder joined into one continuous book. Its concat list is removed when the book
+// is dropped, unless it is kept for the outputs that refer to it.
+struct JoinedBook {
+    list_path: PathBuf,
+    keep_list: bool,
+}
+
+impl Drop for JoinedBook {
+    fn drop(&mut self) {
+        if !self.keep_list {
+            let _ = std::fs::remove_file(&self.list_path);
+        }
+    }
+}
+
+// Joins the audio files of a folder into one continuous book named after the folder.
+// Dry runs keep the list out of the output directory.
+fn join_folder(splitter: &Splitter, folder_path: &Path, scan: &ScanOptions, order_by_tags: bool, dry_run: bool, keep_list: bool,
+               warning: &dyn Fn(&str)) -> Result<JoinedBook, String> {
+    let files = folder_files(folder_path, scan, order_by_tags, warning)?;
+    let name = book_name(folder_path);
+
+    let list_path = if dry_run {
+        splitter.clone().output_dir(std::env::temp_dir()).join_files(&name, &files)?
+    } else {
+        splitter.join_files(&name, &files)?
+    };
+    Ok(JoinedBook { list_path, keep_list })
+}
+
+// Collects the audio files of a folder in the order they are processed: the natural order
+// of their paths, or the order of their disc and track tags. Formats and tags are probed
+// with FFprobe whichever backend analyzes the audio. Skipped subfolders and unreadable
+// tags are reported through `warning`.
+fn folder_files(folder_path: &Path, scan: &ScanOptions, order_by_tags: bool, warning: &dyn Fn(&str)) -> Result<Vec<PathBuf>, String> {
+    let mut files = scan_audio_files(folder_path, scan, &FfmpegBackend, warning)?;
+    if order_by_tags {
+        sort_by_tags(&mut files, &FfmpegBackend, warning);
+    }
+    Ok(files)
+}
+
+// Prints the order in which the files of a folder are processed.
+fn print_file_order(folder_path: &Path, files: &[PathBuf]) {
+    println!("Processing order:");
+    for (number, file) in files.iter().enumerate() {
+        println!("  {:>3}. {}", number + 1, file.strip_prefix(folder_path).unwrap_or(file).display());
+    }
+}
+
+// The name of the book a folder is joined into: the name of the folder.
+fn book_name(folder_path: &Path) -> String {
+    folder_path
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "book".to_string())
+}
+
+// Splits one input, or only reports its segments for a dry run. CUE sheets and
+// split plans drive the split of the audio file they refer to.
+fn process_input(splitter: &Splitter, input_path: &Path, dry_run: bool, target_segments: Option<usize>, log: &FileLog) -> Result<(), String> {
+    let extension = input_path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "cue" => {
+            let (audio_path, analysis) = splitter.analyze_cue_sheet(input_path)?;
+            if dry_run {
+                report_segments(log, &audio_path, &analysis);
+            } else {
+                splitter.split_with_analysis(&audio_path, &analysis)?;
+            }
+        }
+        "json" => {
+            let plan = SplitPlan::load(input_path)?;
+            if dry_run {
+                report_segments(log, &plan.input, &plan.to_analysis()?);
+            } else {
+                splitter.apply_plan(&plan)?;
+            }
+        }
+        _ => match target_segments {
+            Some(target) => split_to_target(splitter, input_path, target, dry_run, log)?,
+            None if dry_run => report_segments(log, input_path, &splitter.analyze(input_path)?),
+            None => {
+                splitter.split_file(input_path)?;
+            }
+        },
+    }
+    Ok(())
+}
+
+// Searches for the thresholds that give `target_segments` segments and splits with them.
+fn split_to_target(splitter: &Splitter, input_path: &Path, target_segments: usize, dry_run: bool, log: &FileLog) -> Result<(), String> {
+    let search = splitter.search_thresholds(input_path, target_segments)?;
+    log.info(&format!("  Status: Using a minimum silence of {:.2}s and a noise threshold of {}dB ({} segments{}).",
+                      search.silence_threshold_seconds, search.noise_threshold_db, search.segment_count,
+                      if search.is_exact(target_segments) { String::new() } else { format!(", {} requested", target_segments) }));
+
+    if dry_run {
+        report_segments(log, input_path, &search.analysis);
+    } else {
+        let splitter = splitter.clone()
+            .silence_threshold_seconds(search.silence_threshold_seconds)
+            .noise_threshold_db(search.noise_threshold_db);
+        splitter.split_with_analysis(input_path, &search.analysis)?;
+    }
+    Ok(())
+}
+
+// Reports the segments of an analysis without splitting.
+fn report_segments(log: &FileLog, input_audio_path: &Path, analysis: &Analysis) {
+    log.info(&format!("  Status: Identified {} audio segments for '{}'.", analysis.split_points.len(), input_audio_path.display()));
+    for segment in analysis.segments() {
+        log.info(&format!("  Segment {}: {:.2}s - {:.2}s (duration {:.2}s){}",
+                          segment.number, segment.start, segment.end, segment.duration(),
+                          segment.title.map(|title| format!(" \"{}\"", title)).unwrap_or_default()));
+    }
+}
+
+// Runs the original prompt-driven wizard.
+fn run_interactive() {
     println!("Welcome to the Audio Splitter!");
     println!("--------------------------------");
     println!("Note: This application requires FFmpeg and FFprobe to be installed");
@@ -23,7 +744,13 @@ fn main() {
     // Main loop to allow the user to process multiple files or batches.
     while process_another {
         let mut input_paths: Vec<PathBuf> = Vec::new();
-        let output_base_dir: PathBuf;
+        let mut jobs = 1; // Files processed at the same time; only asked for folders.
+        let mut file_subfolders = false; // Whether each file's parts get their own subfolder; only asked for folders.
+        let mut scan = ScanOptions::default(); // Which files of a folder are processed.
+        let mut order_by_tags = false; // Whether the files of a folder are ordered by their tags.
+        let mut mirrored_folder = None; // The folder whose tree is mirrored in the output directory.
+        let mut audio_stream = None; // The audio stream to split; only asked for files with video or several.
+        let mut output_format = None; // The format of the split files; only asked for videos.
 
         // Prompt user to choose between single file or folder processing
         let process_type = loop {
@@ -54,6 +781,42 @@ fn main() {
                     println!("Error: File not found or is not a valid file. Please try again.");
                 }
             };
+
+            // Videos and files with several audio tracks need a choice of what to split.
+            if let Ok(streams) = FfmpegBackend.probe_streams(&path)
+                && (streams.video || streams.audio.len() > 1)
+            {
+                if streams.audio.len() > 1 {
+                    println!("The file has {} audio streams:", streams.audio.len());
+                    for (i, stream) in streams.audio.iter().enumerate() {
+                        println!("  {}: {}", i + 1, stream.describe());
+                    }
+                    audio_stream = loop {
+                        print!("Which audio stream should be split? Enter its number or language (press Enter for {}): ",
+                               streams.default_audio_stream() + 1);
+                        io::stdout().flush().unwrap();
+                        let mut stream_str = String::new();
+                        io::stdin().read_line(&mut stream_str).unwrap();
+                        if stream_str.trim().is_empty() {
+                            break None;
+                        }
+                        match AudioStreamChoice::parse(&stream_str).and_then(|choice| choice.select(&streams).map(|_| choice)) {
+                            Ok(choice) => break Some(choice),
+                            Err(e) => println!("Error: {}", e),
+                        }
+                    };
+                }
+                if streams.video {
+                    print!("The file is a video; its parts will contain only the audio. Enter a format for them, e.g. mp3 or m4a (press Enter to keep the audio as it is): ");
+                    io::stdout().flush().unwrap();
+                    let mut format_str = String::new();
+                    io::stdin().read_line(&mut format_str).unwrap();
+                    let extension = format_str.trim().trim_start_matches('.').to_lowercase();
+                    if !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+                        output_format = Some(extension);
+                    }
+                }
+            }
             input_paths.push(path);
         } else { // process_type == "f"
             // Get folder path and collect audio files
@@ -70,22 +833,20 @@ fn main() {
                 }
             };
 
+            print!("Include the files in subfolders (the folder structure is kept in the output)? (y/n): ");
+            io::stdout().flush().unwrap();
+            let mut recursive_response = String::new();
+            io::stdin().read_line(&mut recursive_response).unwrap();
+            scan.recursive = recursive_response.trim().eq_ignore_ascii_case("y");
+
             println!("Status: Scanning folder '{}' for audio files...", folder_path.display());
-            let audio_extensions = ["mp3", "wav", "flac", "aac", "m4a", "ogg"]; // Common audio extensions
-            for entry in std::fs::read_dir(&folder_path).expect("Failed to read directory") {
-                let entry = entry.expect("Failed to read directory entry");
-                let path = entry.path();
-                if path.is_file() {
-                    if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-                        if audio_extensions.contains(&ext.to_lowercase().as_str()) {
-                            input_paths.push(path);
-                        }
-                    }
+            input_paths = match scan_audio_files(&folder_path, &scan, &FfmpegBackend, &|message| eprintln!("Warning: {}", message)) {
+                Ok(files) => files,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    continue; // Loop back to ask for another file/folder
                 }
-            }
-            input_paths.sort_by(|a, b| {
-                a.file_name().cmp(&b.file_name()) // Sort alphabetically by filename
-            });
+            };
 
             if input_paths.is_empty() {
                 println!("No supported audio files found in the specified folder. Please try again.");
@@ -93,10 +854,54 @@ fn main() {
                 continue;
             }
             println!("Status: Found {} audio files in the folder.", input_paths.len());
+
+            print_file_order(&folder_path, &input_paths);
+            print!("Order the files by their disc and track number tags instead? (y/n): ");
+            io::stdout().flush().unwrap();
+            let mut order_response = String::new();
+            io::stdin().read_line(&mut order_response).unwrap();
+            if order_response.trim().eq_ignore_ascii_case("y") {
+                order_by_tags = true;
+                println!("Status: Reading the tags of {} files...", input_paths.len());
+                sort_by_tags(&mut input_paths, &FfmpegBackend, &|message| eprintln!("Warning: {}", message));
+                print_file_order(&folder_path, &input_paths);
+            }
+
+            print!("Treat the files as one continuous book, so chapters can span file boundaries? (y/n): ");
+            io::stdout().flush().unwrap();
+            let mut continuous_response = String::new();
+            io::stdin().read_line(&mut continuous_response).unwrap();
+            if continuous_response.trim().eq_ignore_ascii_case("y") {
+                // The folder itself is joined into one book once the output directory is known.
+                input_paths = vec![folder_path];
+            } else {
+                if scan.recursive {
+                    mirrored_folder = Some(folder_path);
+                }
+                jobs = loop {
+                    print!("How many files should be processed at the same time? (press Enter for 1): ");
+                    io::stdout().flush().unwrap();
+                    let mut jobs_str = String::new();
+                    io::stdin().read_line(&mut jobs_str).unwrap();
+                    match jobs_str.trim() {
+                        "" => break 1,
+                        value => match value.parse::<usize>() {
+                            Ok(count) if count > 0 => break count,
+                            _ => println!("Error: Invalid number. Please enter a positive whole number."),
+                        },
+                    }
+                };
+
+                print!("Put the parts of each file into a subfolder named after the file? (y/n): ");
+                io::stdout().flush().unwrap();
+                let mut subfolders_response = String::new();
+                io::stdin().read_line(&mut subfolders_response).unwrap();
+                file_subfolders = subfolders_response.trim().eq_ignore_ascii_case("y");
+            }
         }
 
         // Get the base output directory (will be used for all splits)
-        output_base_dir = loop {
+        let output_base_dir: PathBuf = loop {
             print!("Enter the base output directory (e.g., output_splits or C:\\MyAudioSplits): ");
             io::stdout().flush().unwrap();
             let mut dir_str = String::new();
@@ -149,17 +954,29 @@ fn main() {
             }
         };
 
+        let mut splitter = console_splitter(&output_base_dir, silence_threshold_seconds, noise_threshold_db);
+        if file_subfolders {
+            splitter = splitter.directory_template("{stem}");
+        }
+        if let Some(folder) = &mirrored_folder {
+            splitter = splitter.mirror_input_tree(folder);
+        }
+        if let Some(choice) = &audio_stream {
+            splitter = splitter.audio_stream(choice.clone());
+        }
+        if let Some(extension) = &output_format {
+            splitter = splitter.output_format(extension.clone());
+        }
+
         // If processing a single file, we offer re-analysis; for folders, we assume batch processing.
         let mut proceed_with_splitting = false;
+        let mut single_file_analysis = None;
         if process_type == "s" {
             loop {
-                println!("\nStatus: Performing initial silence detection for '{}' with threshold {:.2}s and noise {}dB...",
-                         input_paths[0].display(), silence_threshold_seconds, noise_threshold_db);
-                println!("(This might take a while for long audio files)");
-
-                let (detected_silences_for_single_file, total_duration_for_single_file) =
-                    match detect_silences_and_get_total_duration(&input_paths[0], silence_threshold_seconds, noise_threshold_db) {
-                        Ok((silences, duration)) => (silences, duration),
+                println!("\nStatus: Performing initial silence detection for '{}'...", input_paths[0].display());
+                let analysis =
+                    match splitter.analyze(&input_paths[0]) {
+                        Ok(analysis) => analysis,
                         Err(e) => {
                             eprintln!("An error occurred during initial detection: {}", e);
                             // For a single file, if detection fails, allow re-analysis or exit.
@@ -178,19 +995,7 @@ fn main() {
                         }
                     };
 
-                let mut temp_split_points: Vec<f64> = Vec::new();
-                for silence in detected_silences_for_single_file {
-                    if silence.duration >= silence_threshold_seconds {
-                        temp_split_points.push(silence.start + (silence.duration / 2.0));
-                    }
-                }
-
-                // Ensure the last segment of the audio is always included for accurate point count
-                if temp_split_points.is_empty() || temp_split_points.last().map_or(false, |&last_split| last_split < total_duration_for_single_file - 0.01) {
-                    temp_split_points.push(total_duration_for_single_file);
-                }
-
-                println!("Status: Identified {} audio segments to be split for '{}'.", temp_split_points.len(), input_paths[0].display());
+                println!("Status: Identified {} audio segments to be split for '{}'.", analysis.split_points.len(), input_paths[0].display());
 
                 print!("Do you want to (r)e-analyze this file with different settings or (p)roceed to split? (r/p): ");
                 io::stdout().flush().unwrap();
@@ -198,7 +1003,7 @@ fn main() {
                 io::stdin().read_line(&mut choice).unwrap();
                 match choice.trim().to_lowercase().as_str() {
                     "r" => continue,
-                    "p" => { proceed_with_splitting = true; break; },
+                    "p" => { proceed_with_splitting = true; single_file_analysis = Some(analysis); break; },
                     _ => { println!("Invalid choice. Re-analyzing by default..."); continue; }
                 }
             }
@@ -207,19 +1012,66 @@ fn main() {
         }
 
         if proceed_with_splitting {
-            // Process each audio file
-            for audio_file_path in &input_paths {
-                println!("\n--- Processing: {} ---", audio_file_path.display());
-                match perform_analysis_and_split(
-                    audio_file_path,
-                    &output_base_dir,
-                    silence_threshold_seconds,
-                    noise_threshold_db
-                ) {
-                    Ok(_) => println!("Successfully completed processing for {}.", audio_file_path.display()),
-                    Err(e) => eprintln!("An error occurred during processing {}: {}", audio_file_path.display(), e),
+            // Folders are joined into one book up front, so its parts can be found below.
+            // The concat lists are removed once the books are split.
+            let mut joined_books = Vec::new();
+            input_paths.retain_mut(|path| {
+                if !path.is_dir() {
+                    return true;
                 }
-            }
+                match join_folder(&splitter, path, &scan, order_by_tags, false, false, &|message| eprintln!("Warning: {}", message)) {
+                    Ok(book) => {
+                        *path = book.list_path.clone();
+                        joined_books.push(book);
+                        true
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        false
+                    }
+                }
+            });
+
+            // Files of an earlier run over the same inputs are only touched when the user says so.
+            let previous_run_files: usize = input_paths
+                .iter()
+                .map(|path| splitter.previous_run_files(path).map_or(0, |files| files.len()))
+                .sum();
+            let splitter = if previous_run_files > 0 {
+                let policy = loop {
+                    print!("Found {} files of a previous run in the output directory. Do you want to (o)verwrite them, (s)kip the existing files, (c)ontinue the numbering after them or (a)bort? (o/s/c/a): ",
+                           previous_run_files);
+                    io::stdout().flush().unwrap();
+                    let mut policy_str = String::new();
+                    io::stdin().read_line(&mut policy_str).unwrap();
+                    match policy_str.trim().to_lowercase().as_str() {
+                        "o" => break CollisionPolicy::Overwrite,
+                        "s" => break CollisionPolicy::Skip,
+                        "c" => break CollisionPolicy::Continue,
+                        "a" => break CollisionPolicy::Fail,
+                        _ => println!("Invalid choice. Please enter o, s, c or a."),
+                    }
+                };
+                splitter.clone().collision_policy(policy)
+            } else {
+                splitter.clone()
+            };
+
+            // Process each audio file
+            process_batch(&splitter, &input_paths, jobs, |splitter, audio_file_path, log| {
+                // A single file was already analyzed above, so its analysis is reused rather
+                // than detecting the silences again, also when it found nowhere to split.
+                let result = match &single_file_analysis {
+                    Some(analysis) if analysis.has_split_points() => splitter.split_with_analysis(audio_file_path, analysis),
+                    Some(_) => {
+                        log.info(&format!("  No silences detected longer than the specified threshold for '{}'. Skipping splitting for this file.",
+                                          audio_file_path.display()));
+                        Ok(Vec::new())
+                    }
+                    None => splitter.split_file(audio_file_path),
+                };
+                result.map(|_| ())
+            });
         } else {
             // If processing single file and user chose not to proceed after re-analysis prompt
             println!("Skipping audio splitting for the current file.");
@@ -237,223 +1089,114 @@ fn main() {
     println!("\nThank you for using the Audio Splitter! Goodbye.");
 }
 
-// Helper function to determine the next available file index in a directory.
-// It scans for files matching the output prefix and extension, extracts their numbers,
-// and returns the highest number found + 1, or 1 if no matching files exist.
-fn get_next_file_index(output_prefix: &str, output_file_extension: &str) -> Result<usize, String> {
-    // Extract the directory part from the prefix. If no directory is specified,
-    // assume the current directory.
-    let output_dir = PathBuf::from(output_prefix).parent().unwrap_or(Path::new(".")).to_path_buf();
-    let file_prefix_stem = PathBuf::from(output_prefix)
-        .file_stem()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_default();
-
-    // Construct a regex to match files like "prefix_001.ext", "prefix_002.ext", etc.
-    // The `file_prefix_stem` needs to be escaped for regex special characters.
-    let escaped_file_prefix_stem = regex::escape(&file_prefix_stem);
-    let regex_pattern = format!(r"^{}_(?P<index>\d{{3,}})\.{}$", escaped_file_prefix_stem, regex::escape(output_file_extension));
-    let file_regex = Regex::new(&regex_pattern)
-        .map_err(|e| format!("Failed to create regex for file indexing: {}", e))?;
-
-    let mut max_index = 0;
-
-    if output_dir.exists() && output_dir.is_dir() {
-        for entry in std::fs::read_dir(&output_dir)
-            .map_err(|e| format!("Failed to read output directory '{}': {}", output_dir.display(), e))?
-        {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                    if let Some(captures) = file_regex.captures(file_name) {
-                        if let Some(index_str) = captures.name("index") {
-                            if let Ok(index) = index_str.as_str().parse::<usize>() {
-                                if index > max_index {
-                                    max_index = index;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(max_index + 1)
+// Creates a splitter that reports its status messages on the console, together with
+// a live progress line when the console is a terminal.
+fn console_splitter(output_dir: &Path, silence_threshold_seconds: f64, noise_threshold_db: f64) -> Splitter {
+    let splitter = Splitter::new(output_dir)
+        .silence_threshold_seconds(silence_threshold_seconds)
+        .noise_threshold_db(noise_threshold_db)
+        .on_status(|message| {
+            end_progress_line();
+            println!("  {}", message);
+        });
+    if io::stdout().is_terminal() { splitter.on_progress(show_progress) } else { splitter }
 }
 
-// New helper function to detect silences and get total duration from FFmpeg/FFprobe.
-fn detect_silences_and_get_total_duration(
-    input_audio_path: &PathBuf,
-    silence_threshold_seconds: f64,
-    noise_threshold_db: f64,
-) -> Result<(Vec<Silence>, f64), String> {
-    // --- Detect silences using FFmpeg's 'silencedetect' filter ---
-    let output = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(input_audio_path)
-        .arg("-hide_banner")
-        .arg("-loglevel")
-        .arg("debug")
-        .arg("-af")
-        .arg(format!("silencedetect=n={}dB:d={}", noise_threshold_db, silence_threshold_seconds))
-        .arg("-f")
-        .arg("null")
-        .arg("-")
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn ffmpeg. Please ensure FFmpeg is installed and in your PATH. Error: {}", e))?
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for ffmpeg process: {}", e))?;
-
-    if !output.status.success() {
-        let stderr_str = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFmpeg exited with a non-zero status code during silence detection. Stderr:\n{}", stderr_str));
-    }
-
-    let stderr_str = String::from_utf8_lossy(&output.stderr);
-    let re_start = Regex::new(r"silence_start: (?P<start>\d+\.\d+)").unwrap();
-    let re_end = Regex::new(r"silence_end: (?P<end>\d+\.\d+) \| silence_duration: (?P<duration>\d+\.\d+)").unwrap();
-
-    let mut starts: Vec<f64> = Vec::new();
-    let mut detected_silences: Vec<Silence> = Vec::new();
-
-    for line in stderr_str.lines() {
-        if let Some(cap) = re_start.captures(line) {
-            let start = cap["start"].parse::<f64>().map_err(|e| format!("Failed to parse silence start time: {}", e))?;
-            starts.push(start);
-        } else if let Some(cap) = re_end.captures(line) {
-            let end = cap["end"].parse::<f64>().map_err(|e| format!("Failed to parse silence end time: {}", e))?;
-            let duration = cap["duration"].parse::<f64>().map_err(|e| format!("Failed to parse silence duration: {}", e))?;
-
-            if let Some(start) = starts.pop() {
-                detected_silences.push(Silence { start, end, duration });
-            } else {
-                eprintln!("Warning: Found silence_end without a matching silence_start. End: {:.2}s, Duration: {:.2}s", end, duration);
-            }
-        }
-    }
-
-    // Get the total duration of the input audio file using ffprobe.
-    let total_duration_output = Command::new("ffprobe")
-        .arg("-v")
-        .arg("error")
-        .arg("-show_entries")
-        .arg("format=duration")
-        .arg("-of")
-        .arg("default=noprint_wrappers=1:nokey=1")
-        .arg(input_audio_path)
-        .stdout(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn ffprobe. Please ensure FFprobe is installed and in your PATH. Error: {}", e))?
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for ffprobe process: {}", e))?;
+// Redraws the progress line in place.
+fn show_progress(progress: &Progress) {
+    // The trailing spaces wipe out leftovers of a longer previous line.
+    print!("\r  {}   ", progress);
+    io::stdout().flush().unwrap();
+    PROGRESS_LINE_OPEN.store(true, Ordering::SeqCst);
+}
 
-    if !total_duration_output.status.success() {
-        let stderr_str = String::from_utf8_lossy(&total_duration_output.stderr);
-        return Err(format!("FFprobe exited with a non-zero status code. Stderr:\n{}", stderr_str));
+// Finishes an open progress line so the next message starts on a line of its own.
+fn end_progress_line() {
+    if PROGRESS_LINE_OPEN.swap(false, Ordering::SeqCst) {
+        println!();
     }
-
-    let total_duration_str = String::from_utf8_lossy(&total_duration_output.stdout);
-    let total_duration = total_duration_str.trim().parse::<f64>().map_err(|e| format!("Failed to parse total audio duration: {}", e))?;
-
-    Ok((detected_silences, total_duration))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-// Function to handle the entire process of detecting silences and splitting a single audio file.
-// Now takes input_audio_path, base_output_dir, silence_threshold_seconds, and noise_threshold_db as arguments.
-fn perform_analysis_and_split(
-    input_audio_path: &PathBuf,
-    base_output_dir: &PathBuf,
-    silence_threshold_seconds: f64,
-    noise_threshold_db: f64,
-) -> Result<(), String> {
-    // No more prompts here; values are passed in.
-    println!("  Status: Detecting silences in '{}' with threshold {:.2}s and noise {}dB...",
-             input_audio_path.display(), silence_threshold_seconds, noise_threshold_db);
-    println!("  (This might take a while for long audio files)");
-
-    let (detected_silences, total_duration) = detect_silences_and_get_total_duration(
-        input_audio_path,
-        silence_threshold_seconds,
-        noise_threshold_db,
-    )?;
-
-    let mut split_points: Vec<f64> = Vec::new();
-    for silence in detected_silences {
-        if silence.duration >= silence_threshold_seconds {
-            let mid_silence_point = silence.start + (silence.duration / 2.0);
-            split_points.push(mid_silence_point);
-        }
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
     }
 
-    if split_points.is_empty() {
-        println!("  No silences detected longer than the specified threshold for '{}'. Skipping splitting for this file.", input_audio_path.display());
-        return Ok(()); // No splits to make for this file
+    #[test]
+    fn parses_all_flags() {
+        let command = parse_args(&args(&["-i", "book.mp3", "--output=out", "--min-silence", "1.5", "-n", "-35", "--dry-run",
+                                         "--min-segment", "30", "--max-segment=3600", "--continuous", "--accurate", "-j", "4",
+                                         "--title-template", "{album} - {title}", "--no-tags",
+                                         "--name-template", "{album} {index:02}", "--on-collision", "Skip",
+                                         "--subdir", "{artist}/{album}", "-r", "--include", "*.mp3", "--exclude=Extras",
+                                         "--symlinks", "follow", "--hidden", "--order", "Tags", "--formats", "+.DTS, ac3", "--probe",
+                                         "--audio-stream", "eng", "--output-format", ".MP3"])).unwrap();
+        let CliCommand::Run(options) = command else { panic!("expected a run command") };
+        assert_eq!(options.inputs, vec![PathBuf::from("book.mp3")]);
+        assert_eq!(options.output_dir, PathBuf::from("out"));
+        assert_eq!(options.silence_threshold_seconds, 1.5);
+        assert_eq!(options.noise_threshold_db, -35.0);
+        assert!(options.dry_run);
+        assert_eq!(options.min_segment_seconds, Some(30.0));
+        assert_eq!(options.max_segment_seconds, Some(3600.0));
+        assert!(options.continuous);
+        assert_eq!(options.cut_mode, CutMode::Accurate);
+        assert_eq!(options.jobs, 4);
+        assert_eq!(options.title_template, "{album} - {title}");
+        assert!(!options.tag_segments);
+        assert_eq!(options.name_template.as_deref(), Some("{album} {index:02}"));
+        assert_eq!(options.collision_policy, CollisionPolicy::Skip);
+        assert_eq!(options.directory_template.as_deref(), Some("{artist}/{album}"));
+        assert!(options.scan.recursive && options.scan.hidden);
+        assert_eq!(options.scan.include, vec!["*.mp3".to_string()]);
+        assert_eq!(options.scan.exclude, vec!["Extras".to_string()]);
+        assert_eq!(options.scan.symlinks, SymlinkPolicy::Follow);
+        assert!(options.order_by_tags);
+        assert!(options.scan.probe);
+        assert_eq!(options.scan.extensions.len(), AUDIO_EXTENSIONS.len() + 2);
+        assert_eq!(options.scan.extensions[AUDIO_EXTENSIONS.len()..], ["dts".to_string(), "ac3".to_string()]);
+        assert_eq!(options.audio_stream, Some(AudioStreamChoice::Language("eng".to_string())));
+        assert_eq!(options.output_format.as_deref(), Some("mp3"));
     }
 
-    // Ensure the last segment of the audio is always included.
-    if split_points.is_empty() || split_points.last().map_or(false, |&last_split| last_split < total_duration - 0.01) {
-        split_points.push(total_duration);
+    #[test]
+    fn positional_inputs_and_defaults() {
+        let command = parse_args(&args(&["a.mp3", "folder", "-o", "out"])).unwrap();
+        let CliCommand::Run(options) = command else { panic!("expected a run command") };
+        assert_eq!(options.inputs, vec![PathBuf::from("a.mp3"), PathBuf::from("folder")]);
+        assert_eq!(options.silence_threshold_seconds, DEFAULT_SILENCE_THRESHOLD_SECONDS);
+        assert_eq!(options.noise_threshold_db, DEFAULT_NOISE_THRESHOLD_DB);
+        assert!(!options.dry_run);
+        assert_eq!(options.target_segments, None);
+        assert!(!options.continuous);
+        assert_eq!(options.collision_policy, CollisionPolicy::Fail);
+        assert!(!options.scan.recursive);
+        assert_eq!(options.scan.symlinks, SymlinkPolicy::Files);
     }
 
-    println!("  Status: Identified {} audio segments to be split for '{}'.", split_points.len(), input_audio_path.display());
-
-    // --- Split audio using FFmpeg for each determined segment ---
-    let mut current_segment_start_time = 0.0;
-
-    let output_file_extension = input_audio_path
-        .extension()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
-
-    let file_stem = input_audio_path.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("audio_part");
-
-    let output_prefix = base_output_dir.join(file_stem).to_string_lossy().to_string();
-
-    let mut file_index = get_next_file_index(&output_prefix, &output_file_extension)?;
-    println!("  Status: Starting new split files for '{}' from index {}.", input_audio_path.display(), file_index);
-
-
-    for (i, &split_end_time) in split_points.iter().enumerate() {
-        let duration = split_end_time - current_segment_start_time;
-
-        if duration <= 0.01 {
-            current_segment_start_time = split_end_time;
-            continue;
-        }
-
-        let output_file_name = format!("{}_{:03}.{}", output_prefix, file_index, output_file_extension);
-
-        println!("  Status: Splitting part {} (from {:.2}s to {:.2}s, duration {:.2}s) to '{}'...",
-                 i + 1, current_segment_start_time, split_end_time, duration, output_file_name);
-
-        let status = Command::new("ffmpeg")
-            .arg("-i")
-            .arg(input_audio_path)
-            .arg("-ss")
-            .arg(format!("{}", current_segment_start_time))
-            .arg("-t")
-            .arg(format!("{}", duration))
-            .arg("-c")
-            .arg("copy")
-            .arg("-y")
-            .arg(&output_file_name)
-            .status()
-            .map_err(|e| format!("Failed to execute ffmpeg for splitting. Error: {}", e))?;
-
-        if !status.success() {
-            return Err(format!("FFmpeg splitting failed for part {}. Status: {}", i + 1, status));
-        }
-
-        current_segment_start_time = split_end_time;
-        file_index += 1;
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(parse_args(&args(&["a.mp3"])).is_err()); // missing output
+        assert!(parse_args(&args(&["-o", "out"])).is_err()); // missing input
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--min-silence", "0"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--bogus"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--min-segment", "60", "--max-segment", "30"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--target-segments", "0"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--jobs", "0"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--title-template", "{chapter}"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--name-template", "{stem}/{index}"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--subdir", "{index}"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--include", "[mp3"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--symlinks", "always"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--order", "date"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--formats", ","])).is_err());
+        assert!(parse_args(&args(&["a.mp4", "-o", "out", "--audio-stream", "0"])).is_err());
+        assert!(parse_args(&args(&["a.mp4", "-o", "out", "--output-format", "mp3/x"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--on-collision", "merge"])).is_err());
+        assert!(parse_args(&args(&["a.mp3", "-o", "out", "--target-segments", "12", "--min-segment", "60"])).is_err());
     }
-
-    Ok(())
 }
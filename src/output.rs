@@ -0,0 +1,272 @@
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::splitter::Segment;
+use crate::tags::Tags;
+use crate::template::{parse_template, render_template, TemplatePiece};
+
+/// The default name of a split file, without its extension, e.g. `book_001`.
+pub const DEFAULT_NAME_TEMPLATE: &str = "{stem}_{index:03}";
+/// The default name of a titled split file (e.g. from a chapter), e.g. `book_001 - Intro`.
+pub const DEFAULT_TITLED_NAME_TEMPLATE: &str = "{stem}_{index:03} - {title}";
+
+// The placeholders available in name templates.
+const NAME_PLACEHOLDERS: &[&str] = &["stem", "index", "total", "start", "end", "title", "album", "artist"];
+// The placeholders available in directory templates.
+const DIRECTORY_PLACEHOLDERS: &[&str] = &["stem", "album", "artist"];
+
+/// Renders the name (without extension) of the split file for `segment`, which gets file
+/// index `index` and is one of `total` segments cut from the file named `stem` with the tags `source`.
+///
+/// The template can use `{stem}`, `{index}`, `{total}`, `{start}` and `{end}` (as `HH-MM-SS`),
+/// `{title}` (the chapter title, or "Part N"), `{album}` and `{artist}`. Numbers can be
+/// zero-padded, e.g. `{index:03}`. Characters that aren't allowed in file names are replaced.
+pub fn format_file_name(template: &str, segment: &Segment, index: usize, total: usize, stem: &str, source: &Tags) -> Result<String, String> {
+    let name = render_template(template, &|name| match name {
+        "stem" => Some(stem.to_string()),
+        "index" => Some(index.to_string()),
+        "total" => Some(total.to_string()),
+        "start" => Some(format_file_time(segment.start)),
+        "end" => Some(format_file_time(segment.end)),
+        "title" => Some(segment.title.clone().unwrap_or_else(|| format!("Part {}", segment.number))),
+        "album" => Some(source.album().unwrap_or_default().to_string()),
+        "artist" => Some(source.artist().unwrap_or_default().to_string()),
+        _ => None,
+    })?;
+
+    let name = sanitize_file_name(&name);
+    if name.is_empty() {
+        return Err(format!("The name template '{}' gives an empty file name for part {}.", template, segment.number));
+    }
+    Ok(name)
+}
+
+/// Checks that `template` is a valid name template for [`format_file_name`].
+pub fn validate_name_template(template: &str) -> Result<(), String> {
+    for piece in parse_template(template)? {
+        match piece {
+            TemplatePiece::Literal(text) if text.contains(['/', '\\']) => {
+                return Err(format!("The name template '{}' can't contain path separators.", template));
+            }
+            TemplatePiece::Placeholder { name, .. } if !NAME_PLACEHOLDERS.contains(&name) => {
+                return Err(format!("Unknown placeholder '{{{}}}' in template '{}'. Use one of {}.",
+                                   name, template, NAME_PLACEHOLDERS.iter().map(|name| format!("{{{}}}", name)).collect::<Vec<_>>().join(", ")));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Renders the subdirectory (relative to the output directory) that receives the split
+/// files of the input named `stem` with the tags `source`.
+///
+/// The template can use `{stem}`, `{album}` and `{artist}`, and `/` to nest directories,
+/// e.g. `{artist}/{album}`. A directory that comes out empty (e.g. for a missing tag) is
+/// named after the stem instead.
+pub fn format_directory(template: &str, stem: &str, source: &Tags) -> Result<PathBuf, String> {
+    let mut directory = PathBuf::new();
+    for component in template.split(['/', '\\']) {
+        let name = render_template(component, &|name| match name {
+            "stem" => Some(stem.to_string()),
+            "album" => Some(source.album().unwrap_or_default().to_string()),
+            "artist" => Some(source.artist().unwrap_or_default().to_string()),
+            _ => None,
+        })?;
+        // Sanitizing also turns `.` and `..` into nothing, so the result stays inside the output directory.
+        let name = sanitize_file_name(&name);
+        directory.push(if name.is_empty() { sanitize_file_name(stem) } else { name });
+    }
+    Ok(directory)
+}
+
+/// Checks that `template` is a valid directory template for [`format_directory`].
+pub fn validate_directory_template(template: &str) -> Result<(), String> {
+    for piece in parse_template(template)? {
+        if let TemplatePiece::Placeholder { name, .. } = piece
+            && !DIRECTORY_PLACEHOLDERS.contains(&name)
+        {
+            return Err(format!("Unknown placeholder '{{{}}}' in template '{}'. Use one of {}.",
+                               name, template, DIRECTORY_PLACEHOLDERS.iter().map(|name| format!("{{{}}}", name)).collect::<Vec<_>>().join(", ")));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the names `name_template` gives are particular to one input: they have its
+/// `{stem}`, or the album or artist of its tags `source` when it has them. Otherwise the
+/// split files of different inputs can't be told apart by their names.
+pub fn names_identify_input(name_template: &str, source: &Tags) -> Result<bool, String> {
+    Ok(parse_template(name_template)?.iter().any(|piece| match piece {
+        TemplatePiece::Placeholder { name: "stem", .. } => true,
+        TemplatePiece::Placeholder { name: "album", .. } => source.album().is_some_and(|album| !sanitize_file_name(album).is_empty()),
+        TemplatePiece::Placeholder { name: "artist", .. } => source.artist().is_some_and(|artist| !sanitize_file_name(artist).is_empty()),
+        _ => false,
+    }))
+}
+
+/// Lists the files in `output_dir` with the extension whose names fit `name_template`
+/// for the input named `stem` with the tags `source`, such as the split files of an
+/// earlier run, together with the `{index}` read from each name when the template has one.
+///
+/// Finds nothing when the names don't tell the inputs apart (see [`names_identify_input`]),
+/// as the files could belong to any input then.
+pub fn find_split_files(output_dir: &Path, name_template: &str, stem: &str, source: &Tags, output_file_extension: &str) -> Result<Vec<(PathBuf, Option<usize>)>, String> {
+    if !names_identify_input(name_template, source)? {
+        return Ok(Vec::new());
+    }
+
+    // Turn the template into a regex that matches files like "book_001.ext": the stem and
+    // tags are known, the index is captured and the other placeholders match their values.
+    let known_value = |value: Option<&str>| regex::escape(&sanitize_file_name(value.unwrap_or_default()));
+    let mut regex_pattern = String::from("^");
+    let mut has_index = false;
+    for piece in parse_template(name_template)? {
+        match piece {
+            TemplatePiece::Literal(text) => regex_pattern.push_str(&regex::escape(&text)),
+            TemplatePiece::Placeholder { name: "stem", .. } => regex_pattern.push_str(&known_value(Some(stem))),
+            TemplatePiece::Placeholder { name: "album", .. } => regex_pattern.push_str(&known_value(source.album())),
+            TemplatePiece::Placeholder { name: "artist", .. } => regex_pattern.push_str(&known_value(source.artist())),
+            TemplatePiece::Placeholder { name: "index", width } if !has_index => {
+                regex_pattern.push_str(&format!(r"(?P<index>\d{{{},}})", width.max(1)));
+                has_index = true;
+            }
+            TemplatePiece::Placeholder { name: "index" | "total", .. } => regex_pattern.push_str(r"\d+"),
+            TemplatePiece::Placeholder { name: "start" | "end", .. } => regex_pattern.push_str(r"\d{2,}-\d{2}-\d{2}"),
+            TemplatePiece::Placeholder { .. } => regex_pattern.push_str(".*?"),
+        }
+    }
+    regex_pattern.push_str(&format!(r"\.{}$", regex::escape(output_file_extension)));
+    let file_regex = Regex::new(&regex_pattern)
+        .map_err(|e| format!("Failed to create regex for file indexing: {}", e))?;
+
+    let mut files = Vec::new();
+
+    if output_dir.exists() && output_dir.is_dir() {
+        for entry in std::fs::read_dir(output_dir)
+            .map_err(|e| format!("Failed to read output directory '{}': {}", output_dir.display(), e))?
+        {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let path = entry.path();
+            if path.is_file()
+                && let Some(file_name) = path.file_name().and_then(|n| n.to_str())
+                && let Some(captures) = file_regex.captures(file_name)
+            {
+                let index = captures.name("index").and_then(|index_str| index_str.as_str().parse::<usize>().ok());
+                files.push((path, index));
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Determines the next available file index for the split files of the input named `stem`
+/// with the tags `source`, named by `name_template`.
+///
+/// It scans `output_dir` for files with the extension whose names fit the template (see
+/// [`find_split_files`]), reads their `{index}`, and returns the highest number found + 1,
+/// or 1 if no matching files exist or the template has no `{index}`.
+pub fn get_next_file_index(output_dir: &Path, name_template: &str, stem: &str, source: &Tags, output_file_extension: &str) -> Result<usize, String> {
+    let files = find_split_files(output_dir, name_template, stem, source, output_file_extension)?;
+    let max_index = files.into_iter().filter_map(|(_, index)| index).max().unwrap_or(0);
+    Ok(max_index + 1)
+}
+
+// Formats a timestamp for file names as `HH-MM-SS`, as colons aren't allowed everywhere.
+fn format_file_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{:02}-{:02}-{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Makes a title safe to use inside a file name by replacing characters that are
+/// reserved on common file systems.
+pub fn sanitize_file_name(title: &str) -> String {
+    let replaced: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Windows doesn't allow names ending in a dot or space.
+    replaced.trim().trim_end_matches(['.', ' ']).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_reserved_characters() {
+        assert_eq!(sanitize_file_name("Part 1: The \"End\"?"), "Part 1_ The _End__");
+        assert_eq!(sanitize_file_name("  AC/DC...  "), "AC_DC");
+    }
+
+    #[test]
+    fn next_index_counts_titled_files() {
+        let dir = std::env::temp_dir().join(format!("audiobook-splitter-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["book_001.mp3", "book_002 - Chapter 2.mp3", "book_010.m4a", "other_020.mp3"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        assert_eq!(get_next_file_index(&dir, DEFAULT_NAME_TEMPLATE, "book", &Tags::default(), "mp3").unwrap(), 2);
+        assert_eq!(get_next_file_index(&dir, DEFAULT_TITLED_NAME_TEMPLATE, "book", &Tags::default(), "mp3").unwrap(), 3);
+        assert_eq!(get_next_file_index(&dir, DEFAULT_NAME_TEMPLATE, "book", &Tags::default(), "flac").unwrap(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn next_index_follows_custom_templates() {
+        let dir = std::env::temp_dir().join(format!("audiobook-splitter-template-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["Book (00-00-00) 7 of 9.mp3", "Book (00-10-30) 12 of 12.mp3", "Book 13.mp3", "One - 004.mp3", "Two - 009.mp3"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let no_tags = Tags::default();
+
+        assert_eq!(get_next_file_index(&dir, "{stem} ({start}) {index} of {total}", "Book", &no_tags, "mp3").unwrap(), 13);
+        assert_eq!(find_split_files(&dir, "{stem} {index}", "Book", &no_tags, "mp3").unwrap(), vec![(dir.join("Book 13.mp3"), Some(13))]);
+
+        // Without the stem, only the input's own tags tell its files from those of other inputs.
+        let album = |album: &str| Tags { entries: vec![("album".to_string(), album.to_string())], cover_art: false };
+        assert_eq!(get_next_file_index(&dir, "{album} - {index:03}", "Book", &album("One"), "mp3").unwrap(), 5);
+        assert_eq!(get_next_file_index(&dir, "{album} - {index:03}", "Book", &album("Three"), "mp3").unwrap(), 1);
+        assert!(find_split_files(&dir, "{album} - {index:03}", "Book", &no_tags, "mp3").unwrap().is_empty());
+        assert!(find_split_files(&dir, "{title}", "Book", &no_tags, "mp3").unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_names_follow_the_template() {
+        let segment = Segment { number: 2, start: 3725.5, end: 4000.0, title: Some("Intro: Part 1/2".to_string()) };
+        let source = Tags { entries: vec![("album".to_string(), "Book".to_string())], cover_art: false };
+        assert_eq!(format_file_name(DEFAULT_TITLED_NAME_TEMPLATE, &segment, 7, 9, "book", &source).unwrap(), "book_007 - Intro_ Part 1_2");
+        assert_eq!(format_file_name("{album} {index:02}of{total:02} [{start} to {end}]", &segment, 7, 9, "book", &source).unwrap(),
+                   "Book 07of09 [01-02-05 to 01-06-40]");
+        assert!(format_file_name("{artist}", &segment, 7, 9, "book", &source).is_err());
+
+        assert!(validate_name_template(DEFAULT_NAME_TEMPLATE).is_ok());
+        assert!(validate_name_template("{stem}/{index}").is_err());
+        assert!(validate_name_template("{chapter}").is_err());
+    }
+
+    #[test]
+    fn directories_follow_the_template() {
+        let source = Tags { entries: vec![("album".to_string(), "Book: One".to_string())], cover_art: false };
+        assert_eq!(format_directory("{stem}", "book", &source).unwrap(), PathBuf::from("book"));
+        assert_eq!(format_directory("{artist}/{album}", "book", &source).unwrap(), PathBuf::from("book/Book_ One"));
+        assert_eq!(format_directory("../{album}", "book", &source).unwrap(), PathBuf::from("book/Book_ One"));
+
+        assert!(validate_directory_template("{artist}/{album}").is_ok());
+        assert!(validate_directory_template("{index}").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::silence::Silence;
+use crate::splitter::Analysis;
+
+/// An editable description of how one input will be split.
+///
+/// A plan is written as JSON by the planning stage, can be reviewed and tweaked by
+/// hand (moving, adding or removing split points and titles), and is then applied
+/// with [`Splitter::apply_plan`](crate::Splitter::apply_plan).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitPlan {
+    pub input: PathBuf,                 // The audio file to split.
+    pub silence_threshold_seconds: f64, // The minimum silence length used for detection.
+    pub noise_threshold_db: f64,        // The noise threshold used for detection.
+    pub total_duration: f64,            // Total duration of the input in seconds.
+    #[serde(default)]
+    pub silences: Vec<Silence>,         // The detected silences, for reference.
+    pub split_points: Vec<f64>,         // Segment end times in seconds.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub titles: Vec<Option<String>>,    // Title of the segment ending at the matching split point.
+}
+
+impl SplitPlan {
+    /// Creates a plan from an analysis of `input`.
+    pub fn new(input: &Path, silence_threshold_seconds: f64, noise_threshold_db: f64, analysis: &Analysis) -> Self {
+        SplitPlan {
+            // Absolute paths keep the plan usable from any working directory.
+            input: input.canonicalize().unwrap_or_else(|_| input.to_path_buf()),
+            silence_threshold_seconds,
+            noise_threshold_db,
+            total_duration: analysis.total_duration,
+            silences: analysis.silences.clone(),
+            split_points: analysis.split_points.clone(),
+            // Without any titles, split points can be added and removed on their own.
+            titles: if analysis.titles.iter().any(Option::is_some) { analysis.titles.clone() } else { Vec::new() },
+        }
+    }
+
+    /// Reads a plan from a JSON file. A relative `input` is relative to the directory of
+    /// the plan, like the `FILE` of a CUE sheet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read split plan '{}': {}", path.display(), e))?;
+        let mut plan: SplitPlan = serde_json::from_str(&contents).map_err(|e| format!("Failed to parse split plan '{}': {}", path.display(), e))?;
+        if plan.input.is_relative() {
+            plan.input = path.parent().unwrap_or(Path::new(".")).join(&plan.input);
+        }
+        Ok(plan)
+    }
+
+    /// Writes the plan as pretty-printed JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize split plan: {}", e))?;
+        std::fs::write(path, json + "\n").map_err(|e| format!("Failed to write split plan '{}': {}", path.display(), e))
+    }
+
+    /// Validates the (possibly hand-edited) split points and turns the plan back
+    /// into an analysis. A final split point at the end of the audio is added if missing.
+    ///
+    /// Titles belong to the split point at the same position, so a plan with titles
+    /// needs one (or `null`) for every split point.
+    pub fn to_analysis(&self) -> Result<Analysis, String> {
+        let mut split_points = self.split_points.clone();
+        let mut titles = self.titles.clone();
+        if titles.is_empty() {
+            titles.resize(split_points.len(), None);
+        } else if titles.len() != split_points.len() {
+            return Err(format!("Invalid split plan: {} titles for {} split points. Please add or remove a title (or null) with each split point.",
+                               titles.len(), split_points.len()));
+        }
+
+        let mut previous = 0.0;
+        for &point in &split_points {
+            if !point.is_finite() || point <= previous {
+                return Err(format!("Invalid split plan: split point {} must be greater than {}.", point, previous));
+            }
+            if point > self.total_duration + 0.01 {
+                return Err(format!("Invalid split plan: split point {} is beyond the end of the audio ({}).", point, self.total_duration));
+            }
+            previous = point;
+        }
+        if split_points.last().is_none_or(|&last_split| last_split < self.total_duration - 0.01) {
+            split_points.push(self.total_duration);
+            titles.push(None);
+        }
+
+        Ok(Analysis {
+            silences: self.silences.clone(),
+            total_duration: self.total_duration,
+            split_points,
+            titles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(split_points: Vec<f64>) -> SplitPlan {
+        SplitPlan {
+            input: PathBuf::from("book.mp3"),
+            silence_threshold_seconds: 2.0,
+            noise_threshold_db: -40.0,
+            total_duration: 100.0,
+            silences: Vec::new(),
+            titles: split_points.iter().map(|_| Some("Intro".to_string())).collect(),
+            split_points,
+        }
+    }
+
+    #[test]
+    fn plan_round_trips_through_json() {
+        let plan = plan(vec![30.0, 100.0]);
+        let json = serde_json::to_string(&plan).unwrap();
+        assert_eq!(serde_json::from_str::<SplitPlan>(&json).unwrap(), plan);
+    }
+
+    #[test]
+    fn hand_written_plan_gets_final_split_point() {
+        let json = r#"{"input": "book.mp3", "silence_threshold_seconds": 2, "noise_threshold_db": -40,
+                       "total_duration": 100, "split_points": [30, 65.5]}"#;
+        let analysis = serde_json::from_str::<SplitPlan>(json).unwrap().to_analysis().unwrap();
+        assert_eq!(analysis.split_points, vec![30.0, 65.5, 100.0]);
+        assert_eq!(analysis.titles, vec![None, None, None]);
+    }
+
+    #[test]
+    fn titles_must_match_the_split_points() {
+        let mut plan = plan(vec![30.0, 100.0]);
+        assert_eq!(plan.to_analysis().unwrap().titles.len(), 2);
+        plan.split_points.insert(1, 60.0);
+        assert!(plan.to_analysis().is_err());
+        plan.titles.clear();
+        assert_eq!(plan.to_analysis().unwrap().titles, vec![None, None, None]);
+    }
+
+    #[test]
+    fn relative_input_is_relative_to_the_plan() {
+        let dir = std::env::temp_dir().join(format!("audiobook-splitter-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut plan = plan(vec![30.0, 100.0]);
+        plan.save(dir.join("book.plan.json")).unwrap();
+        assert_eq!(SplitPlan::load(dir.join("book.plan.json")).unwrap().input, dir.join("book.mp3"));
+
+        plan.input = PathBuf::from("/audio/book.mp3");
+        plan.save(dir.join("book.plan.json")).unwrap();
+        assert_eq!(SplitPlan::load(dir.join("book.plan.json")).unwrap().input, PathBuf::from("/audio/book.mp3"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unordered_or_out_of_range_points() {
+        assert!(plan(vec![30.0, 20.0, 100.0]).to_analysis().is_err());
+        assert!(plan(vec![30.0, 120.0]).to_analysis().is_err());
+        assert!(plan(vec![0.0, 100.0]).to_analysis().is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::time::Duration;
+
+/// The long-running passes over an input that report their progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStage {
+    /// Decoding the input to find its silences.
+    Detecting,
+    /// Writing the segments.
+    Splitting,
+}
+
+/// A snapshot of how far a pass over one input has come.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub stage: ProgressStage,
+    pub processed_seconds: f64, // How much of the input has been processed, in seconds of audio.
+    pub total_seconds: f64,     // The total duration of the input.
+    pub elapsed: Duration,      // Wall-clock time since the pass started.
+}
+
+impl Progress {
+    /// The processed share of the input, from 0.0 to 1.0.
+    pub fn fraction(&self) -> f64 {
+        if self.total_seconds > 0.0 {
+            (self.processed_seconds / self.total_seconds).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// The estimated time until the pass is done, assuming the speed so far stays the same.
+    /// Unknown until some of the input has been processed.
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction();
+        if fraction <= 0.0 {
+            return None;
+        }
+        Some(self.elapsed.mul_f64((1.0 - fraction) / fraction))
+    }
+}
+
+impl fmt::Display for Progress {
+    /// Formats the progress as e.g. `Detecting silences: 42.0% (elapsed 00:01:23, ETA 00:01:55)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self.stage {
+            ProgressStage::Detecting => "Detecting silences",
+            ProgressStage::Splitting => "Splitting",
+        };
+        let eta = self.eta().map_or_else(|| "--:--:--".to_string(), format_duration);
+        write!(f, "{}: {:5.1}% (elapsed {}, ETA {})", stage, self.fraction() * 100.0, format_duration(self.elapsed), eta)
+    }
+}
+
+// Formats a duration as `HH:MM:SS`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eta_extrapolates_the_speed_so_far() {
+        let progress = Progress {
+            stage: ProgressStage::Detecting,
+            processed_seconds: 900.0,
+            total_seconds: 3600.0,
+            elapsed: Duration::from_secs(30),
+        };
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(90)));
+        assert_eq!(progress.to_string(), "Detecting silences:  25.0% (elapsed 00:00:30, ETA 00:01:30)");
+    }
+
+    #[test]
+    fn eta_is_unknown_before_any_progress() {
+        let progress = Progress {
+            stage: ProgressStage::Splitting,
+            processed_seconds: 0.0,
+            total_seconds: 0.0,
+            elapsed: Duration::from_secs(4000),
+        };
+        assert_eq!(progress.eta(), None);
+        assert_eq!(progress.to_string(), "Splitting:   0.0% (elapsed 01:06:40, ETA --:--:--)");
+    }
+}
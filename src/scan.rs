@@ -0,0 +1,462 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::backend::{FfmpegBackend, MediaBackend};
+use crate::tags::Tags;
+
+/// Audio file extensions picked up when scanning a folder, unless [`ScanOptions`] names others.
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "wav", "flac", "aac", "m4a", "m4b", "ogg", "oga", "opus", "wma", "aiff", "aif", "mka", "webm",
+];
+
+/// How a folder scan treats symbolic links.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SymlinkPolicy {
+    /// Ignore links altogether.
+    Skip,
+    /// Pick up links to audio files, but don't descend into linked folders.
+    #[default]
+    Files,
+    /// Follow links to files and folders. Folders that were already scanned, e.g. through
+    /// a link pointing back up the tree, are skipped.
+    Follow,
+}
+
+/// Which files a folder scan picks up.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    pub recursive: bool,         // Descend into subfolders.
+    pub include: Vec<String>,    // Glob patterns of the files to pick up; empty picks up every audio file.
+    pub exclude: Vec<String>,    // Glob patterns of the files and folders to leave out.
+    pub symlinks: SymlinkPolicy, // What to do with symbolic links.
+    pub hidden: bool,            // Pick up hidden files and folders (names starting with a dot).
+    pub extensions: Vec<String>, // Extensions of the audio files, lowercase without dot; empty means `AUDIO_EXTENSIONS`.
+    pub probe: bool,             // Probe files with other extensions by their content and pick up those with audio.
+}
+
+/// Collects the supported audio files directly inside a folder, in the natural order of
+/// their names (see [`natural_cmp`]).
+pub fn collect_audio_files(folder_path: &Path) -> Result<Vec<PathBuf>, String> {
+    // Without subfolders there are none to skip, so there is nothing to warn about.
+    scan_audio_files(folder_path, &ScanOptions::default(), &FfmpegBackend, &|_| {})
+}
+
+/// Collects the supported audio files in a folder as chosen by `options`, in the natural
+/// order of their paths (see [`natural_cmp`]).
+///
+/// Glob patterns support `*`, `?`, `[abc]`, `[!abc]`, `{a,b}` and `**` for any number of
+/// folders, and ignore case. A pattern with a `/` is matched against the path relative to
+/// `folder_path` (e.g. `**/Disc */*.mp3`), one without against the file or folder name
+/// only (e.g. `*.m4a` or `Extras`). Excluded folders are not descended into.
+///
+/// With `options.probe`, files whose extension isn't an audio one are checked with
+/// `backend`, so e.g. an `.mp4` audiobook or a file without extension is picked up too.
+///
+/// Subfolders that can't be read are skipped and reported through `warning`, so one of
+/// them doesn't stop the rest of a library from being found.
+pub fn scan_audio_files(folder_path: &Path, options: &ScanOptions, backend: &dyn MediaBackend, warning: &dyn Fn(&str)) -> Result<Vec<PathBuf>, String> {
+    let scan = Scan {
+        options,
+        backend,
+        warning,
+        include: options.include.iter().map(|pattern| Glob::new(pattern)).collect::<Result<_, _>>()?,
+        exclude: options.exclude.iter().map(|pattern| Glob::new(pattern)).collect::<Result<_, _>>()?,
+    };
+    let mut visited = HashSet::new();
+    if let Ok(folder) = folder_path.canonicalize() {
+        visited.insert(folder);
+    }
+
+    let mut input_paths: Vec<PathBuf> = Vec::new();
+    let entries = std::fs::read_dir(folder_path).map_err(|e| format!("Failed to read directory '{}': {}", folder_path.display(), e))?;
+    scan.folder(entries, "", &mut visited, &mut input_paths)?;
+    input_paths.sort_by(|a, b| compare_paths(a, b));
+    Ok(input_paths)
+}
+
+/// Compares two names the way people count: runs of digits compare by their value, so
+/// `Track 2` comes before `Track 10` and `Disc 9` before `Disc 10`. Other text compares
+/// case-insensitively.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    while let (Some(a_char), Some(b_char)) = (a_rest.chars().next(), b_rest.chars().next()) {
+        let ordering = if a_char.is_ascii_digit() && b_char.is_ascii_digit() {
+            let a_digits = leading_digits(a_rest);
+            let b_digits = leading_digits(b_rest);
+            a_rest = &a_rest[a_digits.len()..];
+            b_rest = &b_rest[b_digits.len()..];
+            let (a_number, b_number) = (a_digits.trim_start_matches('0'), b_digits.trim_start_matches('0'));
+            // Without leading zeros, a longer number is a larger one.
+            a_number.len().cmp(&b_number.len()).then_with(|| a_number.cmp(b_number))
+        } else {
+            a_rest = &a_rest[a_char.len_utf8()..];
+            b_rest = &b_rest[b_char.len_utf8()..];
+            a_char.to_lowercase().cmp(b_char.to_lowercase())
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    // Names that only differ in case or leading zeros still get a fixed order.
+    a_rest.len().cmp(&b_rest.len()).then_with(|| a.cmp(b))
+}
+
+// The run of ASCII digits `text` starts with.
+fn leading_digits(text: &str) -> &str {
+    &text[..text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len())]
+}
+
+// Compares two paths folder by folder with `natural_cmp`.
+fn compare_paths(a: &Path, b: &Path) -> Ordering {
+    let mut a_components = a.components();
+    let mut b_components = b.components();
+    loop {
+        match (a_components.next(), b_components.next()) {
+            (Some(a_component), Some(b_component)) => {
+                let ordering = natural_cmp(&a_component.as_os_str().to_string_lossy(), &b_component.as_os_str().to_string_lossy());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (a_component, b_component) => return a_component.is_some().cmp(&b_component.is_some()),
+        }
+    }
+}
+
+/// Orders `files` by their disc and track number tags, as read by `backend`.
+///
+/// Files of the same album (or, without an album tag, of the same folder) are ordered by
+/// disc and then track number. Files without a disc number come before the first disc,
+/// and files without a track number after the numbered ones of their disc. Otherwise the
+/// files keep their order, so a library of several books stays grouped by book. Files
+/// whose tags can't be read are reported through `warning` and count as untagged.
+pub fn sort_by_tags(files: &mut [PathBuf], backend: &dyn MediaBackend, warning: &dyn Fn(&str)) {
+    let tags: Vec<Tags> = files
+        .iter()
+        .map(|file| {
+            backend.probe_tags(file).unwrap_or_else(|e| {
+                warning(&format!("Failed to read the tags of '{}', keeping it in name order: {}", file.display(), e));
+                Tags::default()
+            })
+        })
+        .collect();
+    sort_by_probed_tags(files, &tags);
+}
+
+// Orders `files` by the disc and track numbers in their `tags`, see `sort_by_tags`.
+fn sort_by_probed_tags(files: &mut [PathBuf], tags: &[Tags]) {
+    let groups: Vec<String> = files
+        .iter()
+        .zip(tags)
+        .map(|(file, tags)| match tags.get("album") {
+            Some(album) => format!("album:{}", album),
+            None => format!("folder:{}", file.parent().unwrap_or(Path::new("")).display()),
+        })
+        .collect();
+    // Each group stays where its first file is.
+    let group_rank = |group: &String| groups.iter().position(|other| other == group).unwrap_or(0);
+
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by_key(|&i| {
+        let disc = tag_number(&tags[i], &["disc", "discnumber"]).unwrap_or(0);
+        let track = tag_number(&tags[i], &["track", "tracknumber"]).unwrap_or(u32::MAX);
+        (group_rank(&groups[i]), disc, track, i)
+    });
+    let sorted: Vec<PathBuf> = order.iter().map(|&i| files[i].clone()).collect();
+    files.clone_from_slice(&sorted);
+}
+
+// The number in the first of the tags `keys` the file has, e.g. 3 for a track tag of "3/12".
+fn tag_number(tags: &Tags, keys: &[&str]) -> Option<u32> {
+    let value = keys.iter().find_map(|key| tags.get(key))?;
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// Checks that `pattern` is a valid glob pattern for [`ScanOptions`].
+pub fn validate_glob(pattern: &str) -> Result<(), String> {
+    Glob::new(pattern).map(|_| ())
+}
+
+// One folder scan with its compiled patterns.
+struct Scan<'a> {
+    options: &'a ScanOptions,
+    backend: &'a dyn MediaBackend,
+    warning: &'a dyn Fn(&str),
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+}
+
+impl Scan<'_> {
+    // Adds the audio files among `entries` to `input_paths`, descending into subfolders
+    // if the scan is recursive. `relative_dir` is the folder's path below the scanned
+    // folder, with `/` separators.
+    fn folder(&self, entries: std::fs::ReadDir, relative_dir: &str, visited: &mut HashSet<PathBuf>, input_paths: &mut Vec<PathBuf>) -> Result<(), String> {
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative_path = if relative_dir.is_empty() { name.clone() } else { format!("{}/{}", relative_dir, name) };
+
+            let is_symlink = entry.file_type().is_ok_and(|file_type| file_type.is_symlink());
+            if (!self.options.hidden && name.starts_with('.'))
+                || (is_symlink && self.options.symlinks == SymlinkPolicy::Skip)
+                || self.exclude.iter().any(|glob| glob.matches(&name, &relative_path))
+            {
+                continue;
+            }
+
+            if path.is_dir() {
+                if !self.options.recursive || (is_symlink && self.options.symlinks != SymlinkPolicy::Follow) {
+                    continue;
+                }
+                if let Ok(folder) = path.canonicalize()
+                    && !visited.insert(folder)
+                {
+                    continue;
+                }
+                match std::fs::read_dir(&path) {
+                    Ok(entries) => self.folder(entries, &relative_path, visited, input_paths)?,
+                    Err(e) => (self.warning)(&format!("Skipping folder '{}': {}", path.display(), e)),
+                }
+            } else if path.is_file()
+                && (self.include.is_empty() || self.include.iter().any(|glob| glob.matches(&name, &relative_path)))
+                && self.is_audio_file(&path)?
+            {
+                input_paths.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    // Whether `path` is an audio file: by its extension, or by its content if the scan probes.
+    fn is_audio_file(&self, path: &Path) -> Result<bool, String> {
+        let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+        let known = if self.options.extensions.is_empty() {
+            AUDIO_EXTENSIONS.contains(&extension.as_str())
+        } else {
+            self.options.extensions.contains(&extension)
+        };
+        if known || !self.options.probe {
+            return Ok(known);
+        }
+        Ok(self.backend.probe_audio_format(path)?.is_some())
+    }
+}
+
+// Whether `extension` is that of a known audio format.
+pub(crate) fn is_audio_extension(extension: &str) -> bool {
+    AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+}
+
+// A compiled glob pattern.
+struct Glob {
+    regex: Regex,
+    whole_path: bool, // Whether the pattern is matched against the relative path rather than the name.
+}
+
+impl Glob {
+    fn new(pattern: &str) -> Result<Glob, String> {
+        let mut regex = String::from("(?i)^");
+        let mut chars = pattern.chars().peekable();
+        let mut open_braces = 0;
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    // `**/` also matches no folder at all.
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '[' => {
+                    let mut class = String::new();
+                    let mut closed = false;
+                    if chars.peek() == Some(&'!') {
+                        chars.next();
+                        class.push('^');
+                    }
+                    for c in chars.by_ref() {
+                        match c {
+                            ']' if !class.is_empty() && class != "^" => {
+                                closed = true;
+                                break;
+                            }
+                            '-' => class.push('-'),
+                            c => class.push_str(&regex::escape(&c.to_string())),
+                        }
+                    }
+                    if !closed {
+                        return Err(format!("Unclosed '[' in pattern '{}'.", pattern));
+                    }
+                    regex.push_str(&format!("[{}]", class));
+                }
+                '{' => {
+                    open_braces += 1;
+                    regex.push_str("(?:");
+                }
+                ',' if open_braces > 0 => regex.push('|'),
+                '}' if open_braces > 0 => {
+                    open_braces -= 1;
+                    regex.push(')');
+                }
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        if open_braces > 0 {
+            return Err(format!("Unclosed '{{' in pattern '{}'.", pattern));
+        }
+        regex.push('$');
+
+        let regex = Regex::new(&regex).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+        Ok(Glob { regex, whole_path: pattern.contains('/') })
+    }
+
+    fn matches(&self, name: &str, relative_path: &str) -> bool {
+        self.regex.is_match(if self.whole_path { relative_path } else { name })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::silence::Silence;
+    use crate::splitter::Segment;
+
+    // Recognizes MP3 audio by its ID3 header, whatever the extension, and reads no tags.
+    struct ContentBackend;
+
+    impl MediaBackend for ContentBackend {
+        fn probe_duration(&self, _input: &Path) -> Result<f64, String> {
+            Err("not needed".to_string())
+        }
+
+        fn detect_silences(&self, _input: &Path, _silence_threshold_seconds: f64, _noise_threshold_db: f64) -> Result<Vec<Silence>, String> {
+            Err("not needed".to_string())
+        }
+
+        fn extract_segment(&self, _input: &Path, _segment: &Segment, _output: &Path) -> Result<(), String> {
+            Err("not needed".to_string())
+        }
+
+        fn probe_tags(&self, _input: &Path) -> Result<Tags, String> {
+            Err("no tags".to_string())
+        }
+
+        fn probe_audio_format(&self, input: &Path) -> Result<Option<String>, String> {
+            let content = std::fs::read(input).map_err(|e| e.to_string())?;
+            Ok(content.starts_with(b"ID3").then(|| "mp3".to_string()))
+        }
+    }
+
+    #[test]
+    fn globs_match_names_or_relative_paths() {
+        let glob = |pattern: &str| Glob::new(pattern).unwrap();
+        assert!(glob("*.mp3").matches("01 Intro.MP3", "Author/Book/01 Intro.MP3"));
+        assert!(!glob("*.mp3").matches("cover.jpg", "cover.jpg"));
+        assert!(glob("Disc ?").matches("Disc 2", "Book/Disc 2"));
+        assert!(glob("[!._]*.{mp3,m4a}").matches("a.m4a", "a.m4a"));
+        assert!(!glob("[!._]*.{mp3,m4a}").matches("_a.m4a", "_a.m4a"));
+        assert!(glob("**/Disc */*.mp3").matches("01.mp3", "Author/Book/Disc 1/01.mp3"));
+        assert!(glob("**/*.mp3").matches("01.mp3", "01.mp3"));
+        assert!(!glob("Author/*.mp3").matches("01.mp3", "Author/Book/01.mp3"));
+
+        assert!(validate_glob("[abc").is_err());
+        assert!(validate_glob("{a,b").is_err());
+        assert!(validate_glob("a+b(c)").is_ok());
+    }
+
+    #[test]
+    fn numbers_sort_by_value() {
+        let mut names = vec!["Track 10.mp3", "track 2.mp3", "Track 1.mp3", "Track 02.mp3", "Track.mp3", "Disc 10", "Disc 9"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, vec!["Disc 9", "Disc 10", "Track 1.mp3", "Track 02.mp3", "track 2.mp3", "Track 10.mp3", "Track.mp3"]);
+
+        let mut paths = vec![PathBuf::from("Book/Disc 10/01.mp3"), PathBuf::from("Book/Disc 2/10.mp3"), PathBuf::from("Book/Disc 2/9.mp3")];
+        paths.sort_by(|a, b| compare_paths(a, b));
+        assert_eq!(paths, vec![PathBuf::from("Book/Disc 2/9.mp3"), PathBuf::from("Book/Disc 2/10.mp3"), PathBuf::from("Book/Disc 10/01.mp3")]);
+    }
+
+    #[test]
+    fn tags_order_files_within_their_album() {
+        let tags = |entries: &[(&str, &str)]| Tags {
+            entries: entries.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+            cover_art: false,
+        };
+        let mut files: Vec<PathBuf> = ["a/intro.mp3", "a/x.mp3", "a/y.mp3", "b/1.mp3", "b/2.mp3", "c/z.mp3"].iter().map(PathBuf::from).collect();
+        let probed = [
+            tags(&[("album", "A")]),
+            tags(&[("album", "A"), ("disc", "2/2"), ("track", "1/5")]),
+            tags(&[("album", "A"), ("disc", "1"), ("track", "07")]),
+            tags(&[]),
+            tags(&[("TRACKNUMBER", "1")]),
+            tags(&[("album", "A"), ("disc", "1/2"), ("track", "2/5")]),
+        ];
+        sort_by_probed_tags(&mut files, &probed);
+        assert_eq!(files, ["a/intro.mp3", "c/z.mp3", "a/y.mp3", "a/x.mp3", "b/2.mp3", "b/1.mp3"].iter().map(PathBuf::from).collect::<Vec<_>>());
+
+        // Files whose tags can't be read are reported and keep their order.
+        let warnings = std::cell::RefCell::new(Vec::new());
+        let files: Vec<PathBuf> = ["a/2.mp3", "a/10.mp3", "b/1.mp3"].iter().map(PathBuf::from).collect();
+        let mut unread = files.clone();
+        sort_by_tags(&mut unread, &ContentBackend, &|message| warnings.borrow_mut().push(message.to_string()));
+        assert_eq!(unread, files);
+        assert_eq!(warnings.borrow().len(), 3);
+    }
+
+    #[test]
+    fn scans_a_library_tree() {
+        let dir = std::env::temp_dir().join(format!("audiobook-splitter-scan-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for file in ["b.mp3", "a.flac", "notes.txt", ".hidden.mp3", "Author/Book/Disc 1/01.mp3", "Author/Book/Disc 2/01.mp3",
+                     "Author/Book/Extras/bonus.mp3", "Author/.cache/x.mp3"] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+
+        assert_eq!(collect_audio_files(&dir).unwrap(), vec![dir.join("a.flac"), dir.join("b.mp3")]);
+
+        let options = ScanOptions { recursive: true, exclude: vec!["Extras".to_string()], ..ScanOptions::default() };
+        assert_eq!(scan_audio_files(&dir, &options, &ContentBackend, &|_| {}).unwrap(), vec![
+            dir.join("a.flac"),
+            dir.join("Author/Book/Disc 1/01.mp3"),
+            dir.join("Author/Book/Disc 2/01.mp3"),
+            dir.join("b.mp3"),
+        ]);
+
+        let options = ScanOptions { recursive: true, include: vec!["**/Disc */*".to_string()], hidden: true, ..ScanOptions::default() };
+        assert_eq!(scan_audio_files(&dir, &options, &ContentBackend, &|_| {}).unwrap().len(), 2);
+        let options = ScanOptions { recursive: true, hidden: true, ..ScanOptions::default() };
+        assert_eq!(scan_audio_files(&dir, &options, &ContentBackend, &|_| {}).unwrap().len(), 7);
+
+        // Other formats are picked up when named, or by their content when probing.
+        std::fs::write(dir.join("book.mp4"), b"ID3\x04").unwrap();
+        std::fs::write(dir.join("cover.jpg"), b"\xff\xd8").unwrap();
+        let options = ScanOptions { extensions: vec!["flac".to_string()], ..ScanOptions::default() };
+        assert_eq!(scan_audio_files(&dir, &options, &ContentBackend, &|_| {}).unwrap(), vec![dir.join("a.flac")]);
+        let options = ScanOptions { probe: true, ..ScanOptions::default() };
+        assert_eq!(scan_audio_files(&dir, &options, &ContentBackend, &|_| {}).unwrap(), vec![dir.join("a.flac"), dir.join("b.mp3"), dir.join("book.mp4")]);
+
+        #[cfg(unix)]
+        {
+            // A link back up the tree is scanned once when followed, and not at all by default.
+            std::os::unix::fs::symlink(&dir, dir.join("Author/loop")).unwrap();
+            let options = ScanOptions { recursive: true, symlinks: SymlinkPolicy::Follow, ..ScanOptions::default() };
+            assert_eq!(scan_audio_files(&dir, &options, &ContentBackend, &|_| {}).unwrap().len(), 5);
+            std::os::unix::fs::symlink(dir.join("b.mp3"), dir.join("link.mp3")).unwrap();
+            assert_eq!(collect_audio_files(&dir).unwrap().len(), 3);
+            let options = ScanOptions { symlinks: SymlinkPolicy::Skip, ..ScanOptions::default() };
+            assert_eq!(scan_audio_files(&dir, &options, &ContentBackend, &|_| {}).unwrap().len(), 2);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
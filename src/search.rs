@@ -0,0 +1,93 @@
+use crate::silence::Silence;
+use crate::splitter::Analysis;
+
+/// The outcome of searching for detection thresholds that yield a target segment count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdSearch {
+    pub silence_threshold_seconds: f64, // The chosen minimum silence length.
+    pub noise_threshold_db: f64,        // The chosen noise threshold.
+    pub segment_count: usize,           // The number of segments these thresholds produce.
+    pub analysis: Analysis,             // The analysis for the chosen thresholds.
+}
+
+impl ThresholdSearch {
+    /// Whether the search hit the target count exactly.
+    pub fn is_exact(&self, target_segments: usize) -> bool {
+        self.segment_count == target_segments
+    }
+}
+
+/// Picks the minimum silence length that turns the silences of one detection pass
+/// into `target_segments` segments.
+///
+/// Raising the minimum silence length only drops the shortest silences, so a single
+/// pass at a low threshold covers every longer threshold at the same noise level.
+/// The threshold is placed halfway between the shortest silence that is kept and the
+/// longest one that is dropped. Returns the threshold and the resulting analysis,
+/// which may miss the target when there are too few silences or equally long ones.
+pub fn choose_silence_threshold(
+    silences: &[Silence],
+    total_duration: f64,
+    target_segments: usize,
+    min_threshold_seconds: f64,
+) -> (f64, Analysis) {
+    let mut durations: Vec<f64> = silences
+        .iter()
+        .map(|silence| silence.duration)
+        .filter(|&duration| duration >= min_threshold_seconds)
+        .collect();
+    durations.sort_by(|a, b| b.total_cmp(a)); // Longest first.
+
+    let wanted_silences = target_segments.saturating_sub(1);
+    let threshold = match (wanted_silences.checked_sub(1).and_then(|i| durations.get(i)), durations.get(wanted_silences)) {
+        (Some(&kept), Some(&dropped)) => (kept + dropped) / 2.0,
+        (Some(&kept), None) => kept,
+        // Not enough silences: keep all of them.
+        (None, _) if wanted_silences > 0 => min_threshold_seconds,
+        // A single segment: drop every silence.
+        (None, Some(&longest)) => longest * 2.0,
+        (None, None) => min_threshold_seconds,
+    };
+    let threshold = threshold.max(min_threshold_seconds);
+
+    (threshold, Analysis::new(silences.to_vec(), total_duration, threshold))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn silences(durations: &[f64]) -> Vec<Silence> {
+        durations
+            .iter()
+            .enumerate()
+            .map(|(i, &duration)| {
+                let start = (i + 1) as f64 * 100.0;
+                Silence { start, end: start + duration, duration }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn threshold_lands_between_kept_and_dropped_silences() {
+        let silences = silences(&[0.6, 3.0, 1.0, 2.5, 4.0]);
+        let (threshold, analysis) = choose_silence_threshold(&silences, 1000.0, 4, 0.5);
+        assert_eq!(threshold, 1.75);
+        assert_eq!(analysis.segments().len(), 4);
+
+        let (threshold, analysis) = choose_silence_threshold(&silences, 1000.0, 1, 0.5);
+        assert_eq!(threshold, 8.0);
+        assert_eq!(analysis.segments().len(), 1);
+    }
+
+    #[test]
+    fn too_few_silences_keep_everything() {
+        let silences = silences(&[1.0, 2.0]);
+        let (threshold, analysis) = choose_silence_threshold(&silences, 1000.0, 10, 0.5);
+        assert_eq!(threshold, 0.5);
+        assert_eq!(analysis.segments().len(), 3);
+
+        let (threshold, _) = choose_silence_threshold(&silences, 1000.0, 3, 0.5);
+        assert_eq!(threshold, 1.0);
+    }
+}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+/// A detected silence region, with all timestamps in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Silence {
+    pub start: f64,    // The starting timestamp of the silence in seconds.
+    pub end: f64,      // The ending timestamp of the silence in seconds.
+    pub duration: f64, // The duration of the silence in seconds.
+}
+
+impl Silence {
+    /// The point in the middle of the silence, where a split is made.
+    pub fn midpoint(&self) -> f64 {
+        self.start + (self.duration / 2.0)
+    }
+}
+
+/// Computes the split points at the middle of every silence that meets the threshold.
+/// The total duration is always appended so the last segment of the audio is included.
+pub fn compute_split_points(silences: &[Silence], silence_threshold_seconds: f64, total_duration: f64) -> Vec<f64> {
+    let mut split_points: Vec<f64> = Vec::new();
+    for silence in silences {
+        if silence.duration >= silence_threshold_seconds {
+            split_points.push(silence.midpoint());
+        }
+    }
+
+    if split_points.last().is_none_or(|&last_split| last_split < total_duration - 0.01) {
+        split_points.push(total_duration);
+    }
+    split_points
+}
+
+/// Finds silences in decoded PCM audio with the same `n`/`d` semantics as FFmpeg's
+/// `silencedetect` filter: a sample frame is silent when every channel stays below the
+/// noise amplitude, and a run of silent frames is reported once it lasts at least the
+/// minimum duration. A silence still open at the end of the audio ends there.
+#[derive(Debug, Clone)]
+pub struct SilenceDetector {
+    sample_rate: u32,
+    noise_amplitude: f32,            // The noise threshold as a linear amplitude (0.0 - 1.0).
+    min_silence_frames: u64,         // The minimum silence length in sample frames.
+    frames_seen: u64,                // Number of sample frames processed so far.
+    silence_start_frame: Option<u64>, // Where the current run of silent frames began.
+    silences: Vec<Silence>,
+}
+
+impl SilenceDetector {
+    pub fn new(sample_rate: u32, silence_threshold_seconds: f64, noise_threshold_db: f64) -> Self {
+        SilenceDetector {
+            sample_rate,
+            noise_amplitude: 10f64.powf(noise_threshold_db / 20.0) as f32,
+            min_silence_frames: (silence_threshold_seconds * sample_rate as f64).round() as u64,
+            frames_seen: 0,
+            silence_start_frame: None,
+            silences: Vec::new(),
+        }
+    }
+
+    /// Feeds interleaved samples in the range -1.0 to 1.0.
+    pub fn push_interleaved(&mut self, samples: &[f32], channels: usize) {
+        for frame in samples.chunks_exact(channels.max(1)) {
+            let silent = frame.iter().all(|sample| sample.abs() < self.noise_amplitude);
+            if silent {
+                self.silence_start_frame.get_or_insert(self.frames_seen);
+            } else if let Some(start_frame) = self.silence_start_frame.take() {
+                self.close_silence(start_frame);
+            }
+            self.frames_seen += 1;
+        }
+    }
+
+    /// The silences detected so far. A silence still open at the end of the audio is
+    /// only added by [`finish`](Self::finish).
+    pub fn silences(&self) -> &[Silence] {
+        &self.silences
+    }
+
+    /// The sample rate the detector counts frames in.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The total duration of the audio fed so far, in seconds.
+    pub fn duration(&self) -> f64 {
+        self.frames_seen as f64 / self.sample_rate as f64
+    }
+
+    /// Ends the audio and returns every detected silence.
+    pub fn finish(mut self) -> Vec<Silence> {
+        if let Some(start_frame) = self.silence_start_frame.take() {
+            self.close_silence(start_frame);
+        }
+        self.silences
+    }
+
+    // Records the silence from `start_frame` up to the current frame if it is long enough.
+    fn close_silence(&mut self, start_frame: u64) {
+        if self.frames_seen - start_frame >= self.min_silence_frames {
+            let start = start_frame as f64 / self.sample_rate as f64;
+            let end = self.duration();
+            self.silences.push(Silence { start, end, duration: end - start });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn silence(start: f64, end: f64) -> Silence {
+        Silence { start, end, duration: end - start }
+    }
+
+    #[test]
+    fn split_points_are_silence_midpoints_plus_end() {
+        let silences = [silence(10.0, 12.0), silence(20.0, 21.0), silence(30.0, 34.0)];
+        assert_eq!(compute_split_points(&silences, 2.0, 40.0), vec![11.0, 32.0, 40.0]);
+    }
+
+    #[test]
+    fn no_silences_yields_single_segment() {
+        assert_eq!(compute_split_points(&[], 2.0, 40.0), vec![40.0]);
+    }
+
+    #[test]
+    fn detector_reports_silences_meeting_both_thresholds() {
+        // 10 frames per second of stereo audio; -20dB is an amplitude of 0.1.
+        let mut detector = SilenceDetector::new(10, 1.0, -20.0);
+        let mut samples = Vec::new();
+        samples.extend([0.5f32; 2 * 10]);                     // 0.0s - 1.0s loud
+        samples.extend([0.01f32; 2 * 15]);                    // 1.0s - 2.5s silent
+        samples.extend([0.5f32; 2 * 10]);                     // 2.5s - 3.5s loud
+        samples.extend([0.01f32; 2 * 5]);                     // 3.5s - 4.0s silent, too short
+        samples.extend([0.5f32, 0.01].repeat(10));            // 4.0s - 5.0s one channel loud
+        samples.extend([0.0f32; 2 * 20]);                     // 5.0s - 7.0s silent until the end
+        detector.push_interleaved(&samples, 2);
+
+        assert_eq!(detector.duration(), 7.0);
+        assert_eq!(detector.finish(), vec![
+            Silence { start: 1.0, end: 2.5, duration: 1.5 },
+            Silence { start: 5.0, end: 7.0, duration: 2.0 },
+        ]);
+    }
+
+    #[test]
+    fn trailing_silence_does_not_add_empty_segment() {
+        let silences = [silence(38.0, 40.0)];
+        assert_eq!(compute_split_points(&silences, 1.0, 39.0), vec![39.0]);
+    }
+}
@@ -0,0 +1,1561 @@
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::backend::{DetectionEvent, FfmpegBackend, MediaBackend};
+use crate::chapters::Chapter;
+use crate::concat::{audio_extension, first_audio_file, write_concat_list, CONCAT_LIST_EXTENSION};
+use crate::constraints::{merge_short_segments, silences_at_split_points, subdivide_long_segments};
+use crate::cue::{parse_cue_sheet, write_cue_sheet};
+use crate::output::{find_split_files, format_directory, format_file_name, get_next_file_index, names_identify_input, sanitize_file_name, DEFAULT_NAME_TEMPLATE, DEFAULT_TITLED_NAME_TEMPLATE};
+use crate::plan::SplitPlan;
+use crate::progress::{Progress, ProgressStage};
+use crate::scan::is_audio_extension;
+use crate::search::{choose_silence_threshold, ThresholdSearch};
+use crate::silence::{compute_split_points, Silence};
+use crate::streams::AudioStreamChoice;
+use crate::tags::{format_segment_title, Tags, DEFAULT_TITLE_TEMPLATE};
+
+// Segments shorter than this (in seconds) are never written.
+const MIN_SEGMENT_DURATION: f64 = 0.01;
+
+// The threshold search detects silences down to this length (in seconds) in one pass...
+const SEARCH_MIN_SILENCE_SECONDS: f64 = 0.5;
+// ...and raises the noise threshold in these steps (in dB) up to the maximum when it finds too few.
+const SEARCH_NOISE_STEP_DB: f64 = 5.0;
+const SEARCH_MAX_NOISE_DB: f64 = -10.0;
+
+// Progress callbacks are called at most this often, except for the final report.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A time range of the input that becomes one output file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub number: usize, // 1-based position of the segment within the input.
+    pub start: f64,    // The starting timestamp of the segment in seconds.
+    pub end: f64,      // The ending timestamp of the segment in seconds.
+    pub title: Option<String>, // The segment title, e.g. from an embedded chapter.
+}
+
+impl Segment {
+    /// The length of the segment in seconds.
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// A segment that was written to disk. In the single-file output modes
+/// ([`OutputMode::ChapteredM4b`], [`OutputMode::CueSheet`] and [`OutputMode::Plan`])
+/// every segment points to the same file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitOutput {
+    pub segment: Segment,
+    pub path: PathBuf,
+}
+
+/// The result of analyzing one input file: where it will be split and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    pub silences: Vec<Silence>, // Silences the split points are placed at.
+    pub total_duration: f64,    // Total duration of the input in seconds.
+    pub split_points: Vec<f64>, // Segment end times, always finishing with the total duration.
+    pub titles: Vec<Option<String>>, // Title of the segment ending at the matching split point, if known.
+}
+
+impl Analysis {
+    /// Builds an analysis from detected silences, keeping only those that meet the threshold.
+    pub fn new(silences: Vec<Silence>, total_duration: f64, silence_threshold_seconds: f64) -> Self {
+        let silences: Vec<Silence> = silences
+            .into_iter()
+            .filter(|silence| silence.duration >= silence_threshold_seconds)
+            .collect();
+        let split_points = compute_split_points(&silences, silence_threshold_seconds, total_duration);
+        let titles = vec![None; split_points.len()];
+        Analysis { silences, total_duration, split_points, titles }
+    }
+
+    /// Builds an analysis that splits exactly at the boundaries of embedded chapters,
+    /// naming each segment after its chapter.
+    pub fn from_chapters(chapters: &[Chapter], total_duration: f64) -> Self {
+        let mut split_points = Vec::new();
+        let mut titles = Vec::new();
+        for (i, chapter) in chapters.iter().enumerate() {
+            // A chapter ends where the next one starts, and the last one at the end of the audio.
+            let end = match chapters.get(i + 1) {
+                Some(next) => next.start,
+                None => total_duration,
+            };
+            split_points.push(end);
+            titles.push(chapter.title.clone());
+        }
+        if split_points.is_empty() {
+            split_points.push(total_duration);
+            titles.push(None);
+        }
+        Analysis { silences: Vec::new(), total_duration, split_points, titles }
+    }
+
+    /// Whether the analysis found anywhere to split the input.
+    pub fn has_split_points(&self) -> bool {
+        self.split_points.len() > 1
+    }
+
+    /// The segments between consecutive split points, skipping empty ones.
+    pub fn segments(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut current_segment_start_time = 0.0;
+        for (i, &split_end_time) in self.split_points.iter().enumerate() {
+            if split_end_time - current_segment_start_time > MIN_SEGMENT_DURATION {
+                segments.push(Segment {
+                    number: i + 1,
+                    start: current_segment_start_time,
+                    end: split_end_time,
+                    title: self.titles.get(i).cloned().flatten(),
+                });
+            }
+            current_segment_start_time = split_end_time;
+        }
+        segments
+    }
+}
+
+/// How the segments of an input are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// One file per segment, in the format of the input.
+    #[default]
+    SeparateFiles,
+    /// A single M4B file with a chapter marker at each split point.
+    ChapteredM4b,
+    /// A CUE sheet with a track at each split point; the audio itself is not touched.
+    CueSheet,
+    /// An editable JSON [`SplitPlan`] that can be applied later; the audio itself is not touched.
+    Plan,
+}
+
+/// How segments are cut out of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CutMode {
+    /// Copy the compressed audio as is. Fast and lossless, but cuts snap to packet
+    /// boundaries, which can clip or repeat a fraction of a second at each split.
+    #[default]
+    Copy,
+    /// Re-encode each segment so it starts and ends exactly at its split points.
+    Accurate,
+}
+
+/// What happens when the output directory already holds files from an earlier run
+/// over the same input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionPolicy {
+    /// Stop with an error and leave the existing files alone. This is the default.
+    #[default]
+    Fail,
+    /// Number from 1 again and replace the existing files.
+    Overwrite,
+    /// Number from 1 again, but only write the files that don't exist yet, e.g. to
+    /// finish an interrupted run.
+    Skip,
+    /// Keep the existing files and number the new ones after them. Single outputs (the
+    /// M4B, CUE sheet or plan) have no numbering and are replaced.
+    Continue,
+}
+
+type StatusCallback = Arc<dyn Fn(&str) + Send + Sync>;
+type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+type SilenceCallback = Arc<dyn Fn(&Silence) + Send + Sync>;
+
+/// Detects silences in audio files and splits them into segments.
+///
+/// Created with [`Splitter::new`] and configured with the builder-style setters.
+#[derive(Clone)]
+pub struct Splitter {
+    output_dir: PathBuf,
+    silence_threshold_seconds: f64,
+    noise_threshold_db: f64,
+    min_segment_seconds: Option<f64>,
+    max_segment_seconds: Option<f64>,
+    secondary_silence_threshold_seconds: Option<f64>,
+    use_chapters: bool,
+    output_mode: OutputMode,
+    cut_mode: CutMode,
+    name_template: Option<String>,
+    directory_template: Option<String>,
+    mirrored_roots: Vec<PathBuf>,
+    collision_policy: CollisionPolicy,
+    tag_segments: bool,
+    title_template: String,
+    audio_stream: Option<AudioStreamChoice>,
+    output_format: Option<String>,
+    backend: Arc<dyn MediaBackend>,
+    on_status: StatusCallback,
+    on_progress: ProgressCallback,
+    on_silence: SilenceCallback,
+}
+
+impl Splitter {
+    /// Default minimum silence length in seconds.
+    pub const DEFAULT_SILENCE_THRESHOLD_SECONDS: f64 = 2.0;
+    /// Default noise threshold in dB.
+    pub const DEFAULT_NOISE_THRESHOLD_DB: f64 = -40.0;
+
+    /// Creates a splitter that writes its segments into `output_dir`.
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Splitter {
+            output_dir: output_dir.into(),
+            silence_threshold_seconds: Self::DEFAULT_SILENCE_THRESHOLD_SECONDS,
+            noise_threshold_db: Self::DEFAULT_NOISE_THRESHOLD_DB,
+            min_segment_seconds: None,
+            max_segment_seconds: None,
+            secondary_silence_threshold_seconds: None,
+            use_chapters: false,
+            output_mode: OutputMode::default(),
+            cut_mode: CutMode::default(),
+            name_template: None,
+            directory_template: None,
+            mirrored_roots: Vec::new(),
+            collision_policy: CollisionPolicy::default(),
+            tag_segments: true,
+            title_template: DEFAULT_TITLE_TEMPLATE.to_string(),
+            audio_stream: None,
+            output_format: None,
+            backend: Arc::new(FfmpegBackend),
+            on_status: Arc::new(|_| {}),
+            on_progress: Arc::new(|_| {}),
+            on_silence: Arc::new(|_| {}),
+        }
+    }
+
+    /// Sets the minimum silence length in seconds for a split point.
+    pub fn silence_threshold_seconds(mut self, seconds: f64) -> Self {
+        self.silence_threshold_seconds = seconds;
+        self
+    }
+
+    /// Sets the noise threshold in dB below which audio counts as silence.
+    pub fn noise_threshold_db(mut self, db: f64) -> Self {
+        self.noise_threshold_db = db;
+        self
+    }
+
+    /// Merges silence-based segments shorter than `seconds` into a neighbour.
+    pub fn min_segment_seconds(mut self, seconds: f64) -> Self {
+        self.min_segment_seconds = Some(seconds);
+        self
+    }
+
+    /// Subdivides silence-based segments longer than `seconds` at the best shorter
+    /// silence inside them.
+    pub fn max_segment_seconds(mut self, seconds: f64) -> Self {
+        self.max_segment_seconds = Some(seconds);
+        self
+    }
+
+    /// Sets the minimum silence length used to find the secondary split points of
+    /// segments longer than the maximum. Defaults to a quarter of the silence threshold.
+    pub fn secondary_silence_threshold_seconds(mut self, seconds: f64) -> Self {
+        self.secondary_silence_threshold_seconds = Some(seconds);
+        self
+    }
+
+    /// Splits on the chapter markers embedded in the input when it has any, falling
+    /// back to silence detection otherwise.
+    pub fn use_chapters(mut self, use_chapters: bool) -> Self {
+        self.use_chapters = use_chapters;
+        self
+    }
+
+    /// Chooses between separate files per segment and a single chaptered M4B.
+    pub fn output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
+    }
+
+    /// Chooses between fast stream copying and sample-accurate re-encoding of the segments.
+    pub fn cut_mode(mut self, cut_mode: CutMode) -> Self {
+        self.cut_mode = cut_mode;
+        self
+    }
+
+    /// Sets the template for the names of the split files (without extension), see
+    /// [`format_file_name`](crate::format_file_name). By default files are named
+    /// [`DEFAULT_NAME_TEMPLATE`](crate::DEFAULT_NAME_TEMPLATE), or
+    /// [`DEFAULT_TITLED_NAME_TEMPLATE`](crate::DEFAULT_TITLED_NAME_TEMPLATE) when the segment has a title.
+    pub fn name_template(mut self, template: impl Into<String>) -> Self {
+        self.name_template = Some(template.into());
+        self
+    }
+
+    /// Puts the split files of each input into a subdirectory of the output directory,
+    /// named by a template like `{stem}` or `{artist}/{album}`, see [`format_directory`](crate::format_directory).
+    pub fn directory_template(mut self, template: impl Into<String>) -> Self {
+        self.directory_template = Some(template.into());
+        self
+    }
+
+    /// Mirrors the folder structure below `root` in the output directory: the outputs of
+    /// `root/Author/Book/part.mp3` go to `Author/Book` in the output directory (plus the
+    /// subdirectory of [`directory_template`](Self::directory_template), if any). Can be
+    /// called for several folders; inputs outside of all of them go to the output directory.
+    pub fn mirror_input_tree(mut self, root: impl Into<PathBuf>) -> Self {
+        self.mirrored_roots.push(root.into());
+        self
+    }
+
+    /// Chooses what happens when the output directory already holds files from an
+    /// earlier run over the same input, see [`previous_run_files`](Self::previous_run_files).
+    pub fn collision_policy(mut self, collision_policy: CollisionPolicy) -> Self {
+        self.collision_policy = collision_policy;
+        self
+    }
+
+    /// Whether separate segment files get their own tags: a title, the track number as
+    /// "n/total", and the album, artist and cover art of the input. Enabled by default.
+    pub fn tag_segments(mut self, tag_segments: bool) -> Self {
+        self.tag_segments = tag_segments;
+        self
+    }
+
+    /// Sets the template for the title tag of each segment, see [`format_segment_title`](crate::format_segment_title).
+    /// Defaults to [`DEFAULT_TITLE_TEMPLATE`](crate::DEFAULT_TITLE_TEMPLATE).
+    pub fn title_template(mut self, template: impl Into<String>) -> Self {
+        self.title_template = template.into();
+        self
+    }
+
+    /// Chooses the audio stream that is analyzed and split in inputs with several, e.g.
+    /// videos with one audio track per language. By default the stream the input marks as
+    /// its default is used, or else the first.
+    pub fn audio_stream(mut self, choice: AudioStreamChoice) -> Self {
+        self.audio_stream = Some(choice);
+        self
+    }
+
+    /// Writes separate segment files in the format of `extension` (e.g. "mp3" or "m4a"),
+    /// converting the audio once before cutting it. By default the segments keep the
+    /// format of the input's audio; video inputs give audio-only segments either way.
+    pub fn output_format(mut self, extension: impl Into<String>) -> Self {
+        self.output_format = Some(extension.into().trim_start_matches('.').to_lowercase());
+        self
+    }
+
+    /// Sets the directory that receives the split files.
+    pub fn output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = output_dir.into();
+        self
+    }
+
+    /// Replaces the media backend, which defaults to [`FfmpegBackend`].
+    pub fn backend(mut self, backend: impl MediaBackend + 'static) -> Self {
+        self.backend = Arc::new(backend);
+        self
+    }
+
+    /// Registers a callback that receives human-readable status messages.
+    pub fn on_status(mut self, callback: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.on_status = Arc::new(callback);
+        self
+    }
+
+    /// Registers a callback that receives the progress of silence detection and splitting
+    /// while they run, a few times per second.
+    pub fn on_progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Arc::new(callback);
+        self
+    }
+
+    /// Registers a callback that receives every silence as soon as detection finds it,
+    /// before the analysis is complete. This includes the extra detection passes for
+    /// secondary silences and the threshold search, whose silences may not all be used.
+    pub fn on_silence(mut self, callback: impl Fn(&Silence) + Send + Sync + 'static) -> Self {
+        self.on_silence = Arc::new(callback);
+        self
+    }
+
+    fn status(&self, message: &str) {
+        (self.on_status)(message);
+    }
+
+    // Returns a callback for the backend that turns the seconds of input processed so
+    // far into throttled progress reports for a pass over `total_seconds` of audio.
+    fn progress_reporter(&self, stage: ProgressStage, total_seconds: f64) -> impl Fn(f64) + '_ {
+        let started = Instant::now();
+        let last_report: Cell<Option<Instant>> = Cell::new(None);
+        let done_reported = Cell::new(false);
+        move |processed_seconds| {
+            let now = Instant::now();
+            let done = processed_seconds >= total_seconds;
+            if done_reported.get() || (!done && last_report.get().is_some_and(|last| now - last < PROGRESS_INTERVAL)) {
+                return;
+            }
+            last_report.set(Some(now));
+            done_reported.set(done);
+            (self.on_progress)(&Progress { stage, processed_seconds, total_seconds, elapsed: now - started });
+        }
+    }
+
+    // Detects silences through the backend while reporting its progress and each silence found.
+    fn detect_silences(&self, input_audio_path: &Path, silence_threshold_seconds: f64, noise_threshold_db: f64, total_duration: f64) -> Result<Vec<Silence>, String> {
+        let report = self.progress_reporter(ProgressStage::Detecting, total_duration);
+        let silences = self.backend.detect_silences_streaming(
+            input_audio_path,
+            silence_threshold_seconds,
+            noise_threshold_db,
+            &mut |event| match event {
+                DetectionEvent::Progress(seconds) => report(seconds),
+                DetectionEvent::Silence(silence) => (self.on_silence)(&silence),
+                DetectionEvent::SkippedPackets(packets) => {
+                    self.status(&format!("Warning: Skipped {} undecodable packets of '{}'.", packets, input_audio_path.display()));
+                }
+            },
+        )?;
+        report(total_duration);
+        Ok(silences)
+    }
+
+    /// Computes the split points of a file, from its embedded chapters if enabled and
+    /// present, otherwise from its silences.
+    pub fn analyze(&self, input_audio_path: impl AsRef<Path>) -> Result<Analysis, String> {
+        let input_audio_path = input_audio_path.as_ref();
+        let audio = self.prepare_audio(input_audio_path, false)?;
+        self.analyze_audio(input_audio_path, &audio.path)
+    }
+
+    // Analyzes `input_audio_path` through `audio`, the file holding its audio.
+    fn analyze_audio(&self, input_audio_path: &Path, audio: &Path) -> Result<Analysis, String> {
+        if self.use_chapters {
+            let chapters = self.backend.probe_chapters(audio)?;
+            if chapters.len() > 1 {
+                self.status(&format!("Status: Using {} embedded chapters of '{}'.", chapters.len(), input_audio_path.display()));
+                let total_duration = self.backend.probe_duration(audio)?;
+                return Ok(Analysis::from_chapters(&chapters, total_duration));
+            }
+            self.status(&format!("Status: No chapters found in '{}', falling back to silence detection.", input_audio_path.display()));
+        }
+
+        self.status(&format!("Status: Detecting silences in '{}' with threshold {:.2}s and noise {}dB...",
+                             input_audio_path.display(), self.silence_threshold_seconds, self.noise_threshold_db));
+        self.status("(This might take a while for long audio files)");
+
+        let total_duration = self.backend.probe_duration(audio)?;
+        let detected_silences = self.detect_silences(
+            audio,
+            self.silence_threshold_seconds,
+            self.noise_threshold_db,
+            total_duration,
+        )?;
+        let mut analysis = Analysis::new(detected_silences, total_duration, self.silence_threshold_seconds);
+        self.apply_segment_length_limits(audio, &mut analysis)?;
+        Ok(analysis)
+    }
+
+    // Enforces the minimum and maximum segment lengths on a silence-based analysis.
+    fn apply_segment_length_limits(&self, input_audio_path: &Path, analysis: &mut Analysis) -> Result<(), String> {
+        let min_segment_seconds = self.min_segment_seconds.unwrap_or(0.0);
+        if min_segment_seconds > 0.0 {
+            let segment_count = analysis.split_points.len();
+            analysis.split_points = merge_short_segments(&analysis.split_points, min_segment_seconds);
+            if analysis.split_points.len() < segment_count {
+                self.status(&format!("Status: Merged {} segments shorter than {:.2}s into their neighbours.",
+                                     segment_count - analysis.split_points.len(), min_segment_seconds));
+            }
+        }
+
+        let mut secondary_silences = Vec::new();
+        if let Some(max_segment_seconds) = self.max_segment_seconds {
+            let too_long = |analysis: &Analysis| analysis.segments().iter().filter(|s| s.duration() > max_segment_seconds).count();
+            if too_long(analysis) > 0 {
+                let secondary_threshold = self.secondary_silence_threshold_seconds.unwrap_or(self.silence_threshold_seconds / 4.0);
+                self.status(&format!("Status: {} segments are longer than {:.2}s, looking for secondary silences of at least {:.2}s...",
+                                     too_long(analysis), max_segment_seconds, secondary_threshold));
+
+                secondary_silences = self.detect_silences(input_audio_path, secondary_threshold, self.noise_threshold_db, analysis.total_duration)?;
+                let segment_count = analysis.split_points.len();
+                analysis.split_points = subdivide_long_segments(&analysis.split_points, max_segment_seconds, min_segment_seconds, &secondary_silences);
+                self.status(&format!("Status: Added {} secondary split points.", analysis.split_points.len() - segment_count));
+
+                if too_long(analysis) > 0 {
+                    self.status(&format!("Warning: {} segments are still longer than {:.2}s because no suitable silence was found inside them.",
+                                         too_long(analysis), max_segment_seconds));
+                }
+            }
+        }
+
+        // The analysis lists the silences it splits at: not the merged ones, but the secondary ones.
+        analysis.silences.extend(secondary_silences);
+        analysis.silences = silences_at_split_points(&analysis.split_points, &analysis.silences);
+        analysis.titles = vec![None; analysis.split_points.len()];
+        Ok(())
+    }
+
+    /// Searches for the minimum silence length and noise threshold that split a file
+    /// into `target_segments` segments, starting from the configured noise threshold.
+    ///
+    /// Each noise level needs a single detection pass; the silence length is then picked
+    /// from the detected silences. The noise threshold is only raised (making detection
+    /// more lenient) while too few silences are found. When the target can't be hit
+    /// exactly, the closest segment count is returned.
+    pub fn search_thresholds(&self, input_audio_path: impl AsRef<Path>, target_segments: usize) -> Result<ThresholdSearch, String> {
+        let input_audio_path = input_audio_path.as_ref();
+        let audio = self.prepare_audio(input_audio_path, false)?;
+        let total_duration = self.backend.probe_duration(&audio.path)?;
+        let min_threshold_seconds = SEARCH_MIN_SILENCE_SECONDS.min(self.silence_threshold_seconds);
+
+        let mut noise_threshold_db = self.noise_threshold_db;
+        let mut best: Option<ThresholdSearch> = None;
+        loop {
+            self.status(&format!("Status: Searching for {} segments in '{}': detecting silences of at least {:.2}s at {}dB...",
+                                 target_segments, input_audio_path.display(), min_threshold_seconds, noise_threshold_db));
+            let silences = self.detect_silences(&audio.path, min_threshold_seconds, noise_threshold_db, total_duration)?;
+            let (silence_threshold_seconds, analysis) =
+                choose_silence_threshold(&silences, total_duration, target_segments, min_threshold_seconds);
+            let segment_count = analysis.segments().len();
+            self.status(&format!("Status: {}dB with a minimum silence of {:.2}s gives {} segments.",
+                                 noise_threshold_db, silence_threshold_seconds, segment_count));
+
+            if best.as_ref().is_none_or(|b| segment_count.abs_diff(target_segments) < b.segment_count.abs_diff(target_segments)) {
+                best = Some(ThresholdSearch { silence_threshold_seconds, noise_threshold_db, segment_count, analysis });
+            }
+
+            // Only a more lenient noise threshold finds more silences, which helps only with too few segments.
+            if segment_count >= target_segments || noise_threshold_db + SEARCH_NOISE_STEP_DB > SEARCH_MAX_NOISE_DB {
+                break;
+            }
+            noise_threshold_db += SEARCH_NOISE_STEP_DB;
+        }
+
+        Ok(best.expect("the search runs at least one pass"))
+    }
+
+    /// Analyzes a file and splits it. Files without any split point are left alone
+    /// and produce no output.
+    pub fn split_file(&self, input_audio_path: impl AsRef<Path>) -> Result<Vec<SplitOutput>, String> {
+        let input_audio_path = input_audio_path.as_ref();
+        // The audio is prepared once for both the analysis and the cutting.
+        let audio = self.prepare_audio(input_audio_path, self.output_mode == OutputMode::SeparateFiles)?;
+        let analysis = self.analyze_audio(input_audio_path, &audio.path)?;
+
+        // A plan or CUE sheet with a single part is still worth writing: split points can be added by hand.
+        if !analysis.has_split_points() && self.output_mode == OutputMode::SeparateFiles {
+            self.status(&format!("No silences detected longer than the specified threshold for '{}'. Skipping splitting for this file.",
+                                 input_audio_path.display()));
+            return Ok(Vec::new()); // No splits to make for this file
+        }
+
+        self.split_audio(input_audio_path, &audio, &analysis)
+    }
+
+    /// Splits a file at the split points of an earlier analysis.
+    pub fn split_with_analysis(&self, input_audio_path: impl AsRef<Path>, analysis: &Analysis) -> Result<Vec<SplitOutput>, String> {
+        let input_audio_path = input_audio_path.as_ref();
+        // CUE sheets and plans only refer to the input, so its audio isn't needed.
+        let audio = match self.output_mode {
+            OutputMode::SeparateFiles => self.prepare_audio(input_audio_path, true)?,
+            OutputMode::ChapteredM4b => self.prepare_audio(input_audio_path, false)?,
+            OutputMode::CueSheet | OutputMode::Plan => PreparedAudio::original(input_audio_path),
+        };
+        self.split_audio(input_audio_path, &audio, analysis)
+    }
+
+    // Splits `input_audio_path` at the split points of `analysis`, cutting the segments from `audio`.
+    fn split_audio(&self, input_audio_path: &Path, audio: &PreparedAudio, analysis: &Analysis) -> Result<Vec<SplitOutput>, String> {
+        if self.directory_template.is_some() || !self.mirrored_roots.is_empty() {
+            let splitter = self.for_input_directory(input_audio_path)?;
+            std::fs::create_dir_all(&splitter.output_dir)
+                .map_err(|e| format!("Failed to create directory '{}': {}", splitter.output_dir.display(), e))?;
+            return splitter.split_audio(input_audio_path, audio, analysis);
+        }
+
+        let segments = analysis.segments();
+        self.status(&format!("Status: Identified {} audio segments to be split for '{}'.",
+                             analysis.split_points.len(), input_audio_path.display()));
+
+        // --- Split audio with the media backend for each determined segment ---
+        let file_stem = input_audio_path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("audio_part");
+
+        match self.output_mode {
+            OutputMode::ChapteredM4b => return self.write_chaptered_m4b(input_audio_path, &audio.path, file_stem, segments),
+            OutputMode::CueSheet => return self.write_cue_sheet(input_audio_path, file_stem, segments),
+            OutputMode::Plan => return self.write_plan(input_audio_path, file_stem, analysis, segments),
+            OutputMode::SeparateFiles => {}
+        }
+        let output_file_extension = self.file_extension(&audio.path);
+
+        // The source tags are only read when the tags or file names need them.
+        let name_templates = self.name_templates();
+        let source_tags = if self.tag_segments || self.names_use_source_tags() { self.probe_source_tags(input_audio_path) } else { Tags::default() };
+
+        for template in &name_templates {
+            if !names_identify_input(template, &source_tags)? {
+                self.status(&format!("Warning: The names from '{}' don't tell the parts of '{}' from those of other inputs, so files of a previous run can't be found. Use {{stem}} in the name template to find them.",
+                                     template, input_audio_path.display()));
+            }
+        }
+        let previous_run = self.find_previous_run(input_audio_path, &source_tags)?;
+        if !previous_run.is_empty() {
+            self.status(&format!("Status: Found {} files of a previous run of '{}' in '{}'.",
+                                 previous_run.len(), input_audio_path.display(), self.output_dir.display()));
+            if self.collision_policy == CollisionPolicy::Fail {
+                return Err(format!("'{}' was already split into '{}' (e.g. '{}'). Choose to overwrite, skip or continue after the existing files.",
+                                   input_audio_path.display(), self.output_dir.display(), previous_run[0].display()));
+            }
+        }
+
+        // Continued numbering starts after the files earlier runs left in the output directory.
+        let mut first_file_index = 1;
+        if self.collision_policy == CollisionPolicy::Continue {
+            for template in &name_templates {
+                first_file_index = first_file_index.max(get_next_file_index(&self.output_dir, template, file_stem, &source_tags, &output_file_extension)?);
+            }
+        }
+        self.status(&format!("Status: Starting new split files for '{}' from index {}.", input_audio_path.display(), first_file_index));
+
+        // Continued parts count the earlier ones, so `{total}` stays the highest index.
+        let total = segments.len() + first_file_index - 1;
+        let mut written: Vec<SplitOutput> = Vec::new();
+        for (file_index, segment) in (first_file_index..).zip(segments) {
+            // By default, titled segments (e.g. chapters) carry their title in the file name.
+            let template = match &self.name_template {
+                Some(template) => template.as_str(),
+                None if segment.title.as_deref().is_some_and(|title| !sanitize_file_name(title).is_empty()) => DEFAULT_TITLED_NAME_TEMPLATE,
+                None => DEFAULT_NAME_TEMPLATE,
+            };
+            let output_file_name = format_file_name(template, &segment, file_index, total, file_stem, &source_tags)?;
+            let path = self.output_dir.join(format!("{}.{}", output_file_name, output_file_extension));
+            if path.exists() && same_file(&path, input_audio_path) {
+                return Err(format!("Refusing to overwrite the input file '{}' with part {}.", input_audio_path.display(), segment.number));
+            }
+            // A template without e.g. `{index}` can give several parts the same name.
+            if let Some(other) = written.iter().find(|output| output.path == path) {
+                return Err(format!("Parts {} and {} of '{}' would both be written to '{}'. Please use a name template with {{index}}, {{start}} or {{end}}.",
+                                   other.segment.number, segment.number, input_audio_path.display(), path.display()));
+            }
+            written.push(SplitOutput { segment, path });
+        }
+
+        // The tags are prepared up front, so a bad title template fails before any audio is cut.
+        let mut segment_tags = if self.tag_segments { Some(self.segment_tags(&source_tags, file_stem, &written, first_file_index, total)?) } else { None };
+
+        // Names that are taken even though they don't fit the previous run (e.g. a template
+        // without `{index}`) are collisions all the same.
+        let existing = written.iter().filter(|output| output.path.exists()).count();
+        match self.collision_policy {
+            CollisionPolicy::Fail | CollisionPolicy::Continue if existing > 0 => {
+                let output = written.iter().find(|output| output.path.exists()).expect("an output exists");
+                return Err(format!("Part {} of '{}' would replace the existing file '{}'.",
+                                   output.segment.number, input_audio_path.display(), output.path.display()));
+            }
+            CollisionPolicy::Skip if existing > 0 => {
+                self.status(&format!("Status: Skipping {} of {} parts of '{}' that already exist.", existing, written.len(), input_audio_path.display()));
+                if let Some(tags) = &mut segment_tags {
+                    let mut outputs = written.iter();
+                    tags.retain(|_| !outputs.next().expect("one output per tag").path.exists());
+                }
+                written.retain(|output| !output.path.exists());
+                if written.is_empty() {
+                    return Ok(written);
+                }
+            }
+            _ => {}
+        }
+
+        let report = self.progress_reporter(ProgressStage::Splitting, analysis.total_duration);
+        match self.cut_mode {
+            // Stream copies of all segments can be made in a single pass over the input.
+            CutMode::Copy => {
+                self.status(&format!("Status: Splitting '{}' into {} parts...", input_audio_path.display(), written.len()));
+                for output in &written {
+                    self.status(&format!("Status: Part {} (from {:.2}s to {:.2}s, duration {:.2}s) goes to '{}'.",
+                                         output.segment.number, output.segment.start, output.segment.end,
+                                         output.segment.duration(), output.path.display()));
+                }
+                self.backend.extract_segments(&audio.path, &written, &report,
+                                              &|message| self.status(&format!("Warning: {}", message)))?;
+            }
+            CutMode::Accurate => {
+                for output in &written {
+                    self.status(&format!("Status: Splitting part {} (from {:.2}s to {:.2}s, duration {:.2}s) to '{}'...",
+                                         output.segment.number, output.segment.start, output.segment.end,
+                                         output.segment.duration(), output.path.display()));
+                    self.backend.extract_segment_accurate(&audio.path, &output.segment, &output.path)?;
+                    report(output.segment.end);
+                }
+            }
+        }
+
+        if let Some(segment_tags) = segment_tags {
+            self.status(&format!("Status: Tagging {} parts of '{}'...", written.len(), input_audio_path.display()));
+            for (output, tags) in written.iter().zip(&segment_tags) {
+                // The audio is already written, so missing tags don't fail the split.
+                if let Err(e) = self.backend.write_tags(input_audio_path, &output.path, tags) {
+                    self.status(&format!("Warning: Failed to tag the parts of '{}'. {}", input_audio_path.display(), e));
+                    break;
+                }
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Returns the files that an earlier run over `input_audio_path` left in the output
+    /// directory: for separate files, those whose names fit the name template for the
+    /// input (none if the names don't tell the inputs apart, see
+    /// [`names_identify_input`](crate::names_identify_input)), and the single output
+    /// file in the other output modes.
+    pub fn previous_run_files(&self, input_audio_path: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+        let input_audio_path = input_audio_path.as_ref();
+        if self.directory_template.is_some() || !self.mirrored_roots.is_empty() {
+            return self.for_input_directory(input_audio_path)?.previous_run_files(input_audio_path);
+        }
+        let source_tags = if self.output_mode == OutputMode::SeparateFiles && self.names_use_source_tags() {
+            self.probe_source_tags(input_audio_path)
+        } else {
+            Tags::default()
+        };
+        self.find_previous_run(input_audio_path, &source_tags)
+    }
+
+    // The files of a previous run over `input_audio_path` (see `previous_run_files`), with
+    // the separate files named after the input's tags `source_tags`.
+    fn find_previous_run(&self, input_audio_path: &Path, source_tags: &Tags) -> Result<Vec<PathBuf>, String> {
+        let file_stem = input_audio_path.file_stem().and_then(|s| s.to_str()).unwrap_or("audio_part");
+        let single_output = match self.output_mode {
+            OutputMode::ChapteredM4b => format!("{}.m4b", file_stem),
+            OutputMode::CueSheet => format!("{}.cue", file_stem),
+            OutputMode::Plan => format!("{}.plan.json", file_stem),
+            OutputMode::SeparateFiles => {
+                let output_file_extension = self.output_extension(input_audio_path)?;
+                let mut files = Vec::new();
+                for template in self.name_templates() {
+                    for (path, _) in find_split_files(&self.output_dir, template, file_stem, source_tags, &output_file_extension)? {
+                        if !files.contains(&path) && !same_file(&path, input_audio_path) {
+                            files.push(path);
+                        }
+                    }
+                }
+                files.sort();
+                return Ok(files);
+            }
+        };
+        let path = self.output_dir.join(single_output);
+        Ok(if path.exists() && !same_file(&path, input_audio_path) { vec![path] } else { Vec::new() })
+    }
+
+    /// Returns the directory that receives the outputs of `input_audio_path`: the output
+    /// directory, or the input's subdirectory of it when the input tree is mirrored or
+    /// there is a directory template.
+    pub fn input_output_dir(&self, input_audio_path: impl AsRef<Path>) -> Result<PathBuf, String> {
+        let input_audio_path = input_audio_path.as_ref();
+        let mut output_dir = self.output_dir.clone();
+        if let Some(relative_dir) = self
+            .mirrored_roots
+            .iter()
+            .find_map(|root| input_audio_path.parent().and_then(|parent| parent.strip_prefix(root).ok()))
+        {
+            output_dir.push(relative_dir);
+        }
+        let Some(template) = &self.directory_template else {
+            return Ok(output_dir);
+        };
+        let file_stem = input_audio_path.file_stem().and_then(|s| s.to_str()).unwrap_or("audio_part");
+        let source_tags = if template.contains("{album") || template.contains("{artist") {
+            self.probe_source_tags(input_audio_path)
+        } else {
+            Tags::default()
+        };
+        Ok(output_dir.join(format_directory(template, file_stem, &source_tags)?))
+    }
+
+    // A copy of this splitter that writes straight into the output subdirectory of `input_audio_path`.
+    fn for_input_directory(&self, input_audio_path: &Path) -> Result<Splitter, String> {
+        let mut splitter = self.clone();
+        splitter.output_dir = self.input_output_dir(input_audio_path)?;
+        splitter.directory_template = None;
+        splitter.mirrored_roots.clear();
+        Ok(splitter)
+    }
+
+    // The extension of the separate files of an input.
+    fn output_extension(&self, input_audio_path: &Path) -> Result<String, String> {
+        Ok(match self.audio_preparation(input_audio_path, true)? {
+            Some(preparation) => preparation.extension,
+            None => self.file_extension(input_audio_path),
+        })
+    }
+
+    // The extension of the files cut from `audio`: its own, unless its audio was only
+    // recognized by its content and the backend knows a format for it.
+    fn file_extension(&self, audio: &Path) -> String {
+        let extension = audio_extension(audio);
+        if is_audio_extension(&extension) {
+            return extension;
+        }
+        match self.backend.probe_audio_format(&first_audio_file(audio)) {
+            Ok(Some(format_extension)) => format_extension,
+            _ => extension,
+        }
+    }
+
+    // Decides whether the audio of `input_audio_path` has to be extracted before it is
+    // analyzed and cut: when it comes with video, with several audio streams, or (if
+    // `convert`) in another format than the one asked for. Returns `None` if the input
+    // can be used as it is.
+    fn audio_preparation(&self, input_audio_path: &Path, convert: bool) -> Result<Option<AudioPreparation>, String> {
+        let output_format = self.output_format.as_ref().filter(|_| convert);
+        let streams = match self.backend.probe_streams(input_audio_path) {
+            Ok(streams) => streams,
+            // Backends that can't tell the streams apart get the input as it is.
+            Err(_) if self.audio_stream.is_none() && output_format.is_none() => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if streams.audio.is_empty() {
+            return Err(format!("'{}' has no audio stream.", input_audio_path.display()));
+        }
+
+        let stream = match &self.audio_stream {
+            Some(choice) => choice.select(&streams)?,
+            None => streams.default_audio_stream(),
+        };
+        let keeps_format = output_format.is_none_or(|format| *format == audio_extension(input_audio_path).to_lowercase());
+        if !streams.video && streams.audio.len() == 1 && keeps_format {
+            return Ok(None);
+        }
+
+        let natural_extension = streams.audio_extension(stream).unwrap_or("mka");
+        let extension = output_format.map_or(natural_extension, |format| format.as_str());
+        Ok(Some(AudioPreparation { stream, extension: extension.to_string(), encode: extension != natural_extension }))
+    }
+
+    // Returns the audio to analyze and cut for `input_audio_path`: the input itself, or
+    // its audio stream extracted to a temporary file (see `audio_preparation`).
+    fn prepare_audio(&self, input_audio_path: &Path, convert: bool) -> Result<PreparedAudio, String> {
+        let Some(preparation) = self.audio_preparation(input_audio_path, convert)? else {
+            return Ok(PreparedAudio::original(input_audio_path));
+        };
+
+        static PREPARED_COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let file_stem = input_audio_path.file_stem().and_then(|s| s.to_str()).unwrap_or("audio_part");
+        let audio = PreparedAudio {
+            path: std::env::temp_dir().join(format!("audiobook-splitter-{}-{}-{}.{}", std::process::id(),
+                                                    PREPARED_COUNTER.fetch_add(1, Ordering::SeqCst), file_stem, preparation.extension)),
+            temporary: true,
+        };
+        self.status(&format!("Status: {} audio stream {} of '{}'...", if preparation.encode { "Converting" } else { "Extracting" },
+                             preparation.stream + 1, input_audio_path.display()));
+        self.backend.extract_audio_stream(input_audio_path, preparation.stream, &audio.path, preparation.encode)?;
+        Ok(audio)
+    }
+
+    // The name templates of the separate files: the custom one, or the defaults for untitled and titled segments.
+    fn name_templates(&self) -> Vec<&str> {
+        match &self.name_template {
+            Some(template) => vec![template.as_str()],
+            None => vec![DEFAULT_NAME_TEMPLATE, DEFAULT_TITLED_NAME_TEMPLATE],
+        }
+    }
+
+    // Whether the names of the split files contain tags of the input.
+    fn names_use_source_tags(&self) -> bool {
+        self.name_templates().iter().any(|template| template.contains("{album") || template.contains("{artist"))
+    }
+
+    // Decides whether the single output file of the non-splitting output modes is written,
+    // according to the collision policy.
+    fn should_write_single_output(&self, input_audio_path: &Path, output_path: &Path) -> Result<bool, String> {
+        if !output_path.exists() {
+            return Ok(true);
+        }
+        match self.collision_policy {
+            CollisionPolicy::Fail => Err(format!("'{}' already exists from a previous run of '{}'. Choose to overwrite or skip it.",
+                                                 output_path.display(), input_audio_path.display())),
+            CollisionPolicy::Skip => {
+                self.status(&format!("Status: Skipping '{}', which already exists.", output_path.display()));
+                Ok(false)
+            }
+            CollisionPolicy::Overwrite => Ok(true),
+            // A single file has no numbering to continue, so it is replaced.
+            CollisionPolicy::Continue => {
+                self.status(&format!("Status: Replacing '{}' from a previous run of '{}'.", output_path.display(), input_audio_path.display()));
+                Ok(true)
+            }
+        }
+    }
+
+    // Reads the tags of the input. Missing tags only make for plainer outputs, so failing to read them isn't an error.
+    fn probe_source_tags(&self, input_audio_path: &Path) -> Tags {
+        self.backend.probe_tags(input_audio_path).unwrap_or_else(|e| {
+            self.status(&format!("Warning: Failed to read the tags of '{}'. {}", input_audio_path.display(), e));
+            Tags::default()
+        })
+    }
+
+    // The tags of each output, based on the tags of the input. The outputs are numbered
+    // like their files, from `first_track` on, out of `total`.
+    fn segment_tags(&self, source: &Tags, file_stem: &str, outputs: &[SplitOutput], first_track: usize, total: usize) -> Result<Vec<Tags>, String> {
+        let mut segment_tags = Vec::new();
+        for (track, output) in (first_track..).zip(outputs) {
+            let title = format_segment_title(&self.title_template, &output.segment, track, total, file_stem, source)?;
+            segment_tags.push(source.for_segment(track, total, &title));
+        }
+        Ok(segment_tags)
+    }
+
+    /// Joins `files` into one continuous input by writing the concat list `<name>.ffconcat`
+    /// into the output directory, and returns the path of the list.
+    ///
+    /// The list can be analyzed and split like a single file, so silences are detected
+    /// across the joined timeline, segments may span file boundaries, and all segments
+    /// share one numbering sequence named after `name`. The list stays in the output
+    /// directory until the caller removes it.
+    pub fn join_files(&self, name: &str, files: &[PathBuf]) -> Result<PathBuf, String> {
+        if files.is_empty() {
+            return Err(format!("No audio files to join into '{}'.", name));
+        }
+
+        let mut entries = Vec::new();
+        for file in files {
+            let duration = self.backend.probe_duration(file)?;
+            // Absolute paths keep the list usable from any working directory.
+            entries.push((file.canonicalize().unwrap_or_else(|_| file.clone()), duration));
+        }
+        let total_duration: f64 = entries.iter().map(|(_, duration)| duration).sum();
+
+        let list_path = self.output_dir.join(format!("{}.{}", name, CONCAT_LIST_EXTENSION));
+        self.status(&format!("Status: Joining {} files ({:.2}s in total) into one continuous book '{}'.",
+                             files.len(), total_duration, list_path.display()));
+        std::fs::write(&list_path, write_concat_list(&entries))
+            .map_err(|e| format!("Failed to write concat list '{}': {}", list_path.display(), e))?;
+        Ok(list_path)
+    }
+
+    /// Creates an editable plan describing how a file would be split.
+    pub fn plan(&self, input_audio_path: impl AsRef<Path>) -> Result<SplitPlan, String> {
+        let input_audio_path = input_audio_path.as_ref();
+        let analysis = self.analyze(input_audio_path)?;
+        Ok(SplitPlan::new(input_audio_path, self.silence_threshold_seconds, self.noise_threshold_db, &analysis))
+    }
+
+    /// Cuts the input of a (possibly hand-edited) plan at its split points.
+    pub fn apply_plan(&self, plan: &SplitPlan) -> Result<Vec<SplitOutput>, String> {
+        self.status(&format!("Status: Applying split plan with {} split points to '{}'.",
+                             plan.split_points.len(), plan.input.display()));
+        self.split_with_analysis(&plan.input, &plan.to_analysis()?)
+    }
+
+    /// Splits the audio file referenced by a CUE sheet at its track boundaries,
+    /// naming each segment after its track title.
+    pub fn split_cue_sheet(&self, cue_path: impl AsRef<Path>) -> Result<Vec<SplitOutput>, String> {
+        let (input_audio_path, analysis) = self.analyze_cue_sheet(cue_path)?;
+        self.split_with_analysis(&input_audio_path, &analysis)
+    }
+
+    /// Reads a CUE sheet and returns the audio file it refers to together with
+    /// an analysis that splits at its track boundaries.
+    pub fn analyze_cue_sheet(&self, cue_path: impl AsRef<Path>) -> Result<(PathBuf, Analysis), String> {
+        let cue_path = cue_path.as_ref();
+        let contents = std::fs::read_to_string(cue_path)
+            .map_err(|e| format!("Failed to read CUE sheet '{}': {}", cue_path.display(), e))?;
+        let sheet = parse_cue_sheet(&contents)?;
+
+        // The FILE entry is relative to the directory of the sheet.
+        let input_audio_path = cue_path.parent().unwrap_or(Path::new(".")).join(&sheet.file);
+        self.status(&format!("Status: Using {} tracks from '{}' for '{}'.",
+                             sheet.tracks.len(), cue_path.display(), input_audio_path.display()));
+
+        let total_duration = self.backend.probe_duration(&input_audio_path)?;
+        Ok((input_audio_path, Analysis::from_chapters(&sheet.chapters(total_duration), total_duration)))
+    }
+
+    // Writes a JSON split plan instead of cutting the audio.
+    fn write_plan(&self, input_audio_path: &Path, file_stem: &str, analysis: &Analysis, segments: Vec<Segment>) -> Result<Vec<SplitOutput>, String> {
+        let output_path = self.output_dir.join(format!("{}.plan.json", file_stem));
+        if !self.should_write_single_output(input_audio_path, &output_path)? {
+            return Ok(Vec::new());
+        }
+        self.status(&format!("Status: Writing split plan with {} segments for '{}' to '{}'...",
+                             segments.len(), input_audio_path.display(), output_path.display()));
+        SplitPlan::new(input_audio_path, self.silence_threshold_seconds, self.noise_threshold_db, analysis).save(&output_path)?;
+
+        Ok(segments
+            .into_iter()
+            .map(|segment| SplitOutput { segment, path: output_path.clone() })
+            .collect())
+    }
+
+    // Writes a CUE sheet describing the segments instead of cutting the audio.
+    fn write_cue_sheet(&self, input_audio_path: &Path, file_stem: &str, segments: Vec<Segment>) -> Result<Vec<SplitOutput>, String> {
+        let output_path = self.output_dir.join(format!("{}.cue", file_stem));
+        if !self.should_write_single_output(input_audio_path, &output_path)? {
+            return Ok(Vec::new());
+        }
+
+        // The sheet refers to the audio by name when it sits next to it, and by full path otherwise.
+        let input_dir = input_audio_path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let file_reference = if same_file(input_dir, &self.output_dir) {
+            input_audio_path.file_name().unwrap_or_default().to_string_lossy().to_string()
+        } else {
+            input_audio_path.canonicalize().unwrap_or_else(|_| input_audio_path.to_path_buf()).to_string_lossy().to_string()
+        };
+
+        self.status(&format!("Status: Writing CUE sheet with {} tracks for '{}' to '{}'...",
+                             segments.len(), input_audio_path.display(), output_path.display()));
+        std::fs::write(&output_path, write_cue_sheet(&file_reference, Some(file_stem), &segments))
+            .map_err(|e| format!("Failed to write CUE sheet '{}': {}", output_path.display(), e))?;
+
+        Ok(segments
+            .into_iter()
+            .map(|segment| SplitOutput { segment, path: output_path.clone() })
+            .collect())
+    }
+
+    // Writes the whole input as one M4B file with a chapter at each split point.
+    fn write_chaptered_m4b(&self, input_audio_path: &Path, audio: &Path, file_stem: &str, segments: Vec<Segment>) -> Result<Vec<SplitOutput>, String> {
+        let output_path = self.output_dir.join(format!("{}.m4b", file_stem));
+        if output_path.exists() && same_file(&output_path, input_audio_path) {
+            return Err(format!("Refusing to overwrite the input file '{}' with the M4B export.", input_audio_path.display()));
+        }
+        if !self.should_write_single_output(input_audio_path, &output_path)? {
+            return Ok(Vec::new());
+        }
+
+        self.status(&format!("Status: Writing {} chapters of '{}' to '{}'...",
+                             segments.len(), input_audio_path.display(), output_path.display()));
+        self.backend.write_chaptered_m4b(audio, &segments, &output_path)?;
+
+        Ok(segments
+            .into_iter()
+            .map(|segment| SplitOutput { segment, path: output_path.clone() })
+            .collect())
+    }
+}
+
+// How the audio of an input is extracted before it is split (see `Splitter::audio_preparation`).
+struct AudioPreparation {
+    stream: usize,     // The position of the audio stream among the input's audio streams.
+    extension: String, // The format of the extracted audio.
+    encode: bool,      // Whether the audio has to be encoded to that format instead of copied.
+}
+
+// The audio that is analyzed and cut for an input: the input itself, or a temporary file
+// with its extracted audio stream that is removed once it has been split.
+struct PreparedAudio {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl PreparedAudio {
+    fn original(input_audio_path: &Path) -> PreparedAudio {
+        PreparedAudio { path: input_audio_path.to_path_buf(), temporary: false }
+    }
+}
+
+impl Drop for PreparedAudio {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+// Whether two paths refer to the same file on disk.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streams::{AudioStream, MediaStreams};
+    use std::sync::Mutex;
+
+    // Backend that returns canned analysis results and records what the splitter asks of
+    // it. Tests start from `mock_backend` and change the fields they need.
+    #[derive(Default)]
+    struct MockBackend {
+        silences: Vec<Silence>, // Each is found when it is at least as long as the minimum silence length.
+        total_duration: f64,
+        detection: Option<fn(f64, f64) -> Vec<Silence>>, // Finds the silences at the given thresholds instead.
+        tags: Tags,
+        streams: Option<MediaStreams>,
+        stream_copy_fails: bool,
+        extracted: Arc<Mutex<Vec<(PathBuf, Segment, PathBuf)>>>, // The input, segment and output of each cut.
+        extracted_streams: Arc<Mutex<Vec<(usize, PathBuf, bool)>>>,
+        tagged: Arc<Mutex<Vec<(PathBuf, Tags)>>>,
+    }
+
+    // A 30s input with a silence from 10s to 12s.
+    fn mock_backend() -> MockBackend {
+        MockBackend {
+            silences: vec![Silence { start: 10.0, end: 12.0, duration: 2.0 }],
+            total_duration: 30.0,
+            ..MockBackend::default()
+        }
+    }
+
+    impl MockBackend {
+        fn cut(&self, input: &Path, segment: &Segment, output: &Path) -> Result<(), String> {
+            std::fs::write(output, b"").map_err(|e| e.to_string())?;
+            self.extracted.lock().unwrap().push((input.to_path_buf(), segment.clone(), output.to_path_buf()));
+            Ok(())
+        }
+    }
+
+    impl MediaBackend for MockBackend {
+        fn probe_duration(&self, _input: &Path) -> Result<f64, String> {
+            Ok(self.total_duration)
+        }
+
+        fn detect_silences(&self, _input: &Path, silence_threshold_seconds: f64, noise_threshold_db: f64) -> Result<Vec<Silence>, String> {
+            if let Some(detection) = self.detection {
+                return Ok(detection(silence_threshold_seconds, noise_threshold_db));
+            }
+            Ok(self.silences.iter().filter(|silence| silence.duration >= silence_threshold_seconds).copied().collect())
+        }
+
+        fn probe_tags(&self, _input: &Path) -> Result<Tags, String> {
+            Ok(self.tags.clone())
+        }
+
+        fn probe_streams(&self, _input: &Path) -> Result<MediaStreams, String> {
+            self.streams.clone().ok_or_else(|| "No streams".to_string())
+        }
+
+        fn extract_audio_stream(&self, _input: &Path, stream: usize, output: &Path, encode: bool) -> Result<(), String> {
+            std::fs::write(output, b"").map_err(|e| e.to_string())?;
+            self.extracted_streams.lock().unwrap().push((stream, output.to_path_buf(), encode));
+            Ok(())
+        }
+
+        fn write_tags(&self, _input: &Path, output: &Path, tags: &Tags) -> Result<(), String> {
+            self.tagged.lock().unwrap().push((output.to_path_buf(), tags.clone()));
+            Ok(())
+        }
+
+        fn extract_segment(&self, input: &Path, segment: &Segment, output: &Path) -> Result<(), String> {
+            if self.stream_copy_fails {
+                return Err("stream copy".to_string());
+            }
+            self.cut(input, segment, output)
+        }
+
+        fn extract_segment_accurate(&self, input: &Path, segment: &Segment, output: &Path) -> Result<(), String> {
+            self.cut(input, segment, output)
+        }
+
+        fn write_chaptered_m4b(&self, input: &Path, segments: &[Segment], output: &Path) -> Result<(), String> {
+            for segment in segments {
+                self.cut(input, segment, output)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn test_output_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("audiobook-splitter-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn split_file_extracts_every_segment_through_the_backend() {
+        let output_dir = test_output_dir("mock-split");
+        let backend = mock_backend();
+        let extracted = backend.extracted.clone();
+        let splitter = Splitter::new(&output_dir).backend(backend);
+
+        let written = splitter.split_file("book.mp3").unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(written[0].path, output_dir.join("book_001.mp3"));
+        assert_eq!(written[1].path, output_dir.join("book_002.mp3"));
+        assert_eq!(extracted.lock().unwrap().len(), 2);
+
+        // A second run finds the files of the first one and stops...
+        assert_eq!(splitter.previous_run_files("book.mp3").unwrap(), vec![output_dir.join("book_001.mp3"), output_dir.join("book_002.mp3")]);
+        assert!(splitter.split_file("book.mp3").is_err());
+        assert_eq!(extracted.lock().unwrap().len(), 2);
+
+        // ...unless told to continue the numbering after the existing files...
+        let splitter = splitter.collision_policy(CollisionPolicy::Continue);
+        let written = splitter.split_file("book.mp3").unwrap();
+        assert_eq!(written[0].path, output_dir.join("book_003.mp3"));
+
+        // ...to only write missing files...
+        std::fs::remove_file(output_dir.join("book_002.mp3")).unwrap();
+        let written = splitter.clone().collision_policy(CollisionPolicy::Skip).split_file("book.mp3").unwrap();
+        assert_eq!(written.len(), 1);
+        assert_eq!(written[0].path, output_dir.join("book_002.mp3"));
+
+        // ...or to replace them.
+        let written = splitter.clone().collision_policy(CollisionPolicy::Overwrite).split_file("book.mp3").unwrap();
+        assert_eq!(written[0].path, output_dir.join("book_001.mp3"));
+        assert_eq!(extracted.lock().unwrap().len(), 7);
+
+        // Continued numbering with a name template counts only the files that fit it.
+        let splitter = splitter.name_template("{stem} - {index:02} of {total} ({start})");
+        let written = splitter.split_file("book.mp3").unwrap();
+        assert_eq!(written[0].path, output_dir.join("book - 01 of 2 (00-00-00).mp3"));
+        assert_eq!(written[1].path, output_dir.join("book - 02 of 2 (00-00-11).mp3"));
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn continued_numbering_counts_the_earlier_parts_in_the_total() {
+        let output_dir = test_output_dir("mock-continue-total");
+        let splitter = Splitter::new(&output_dir).backend(mock_backend()).collision_policy(CollisionPolicy::Continue)
+            .name_template("{stem} - {index:02} of {total} ({start})");
+
+        splitter.split_file("book.mp3").unwrap();
+        let written = splitter.split_file("book.mp3").unwrap();
+        assert_eq!(written[0].path, output_dir.join("book - 03 of 4 (00-00-00).mp3"));
+        assert_eq!(written[1].path, output_dir.join("book - 04 of 4 (00-00-11).mp3"));
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn inputs_sharing_an_output_directory_keep_their_previous_runs_apart() {
+        let output_dir = test_output_dir("mock-shared-dir");
+        let album = |album: &str| Tags { entries: vec![("album".to_string(), album.to_string())], cover_art: false };
+        let splitter = |album_name: &str| {
+            Splitter::new(&output_dir).backend(MockBackend { tags: album(album_name), ..mock_backend() }).name_template("{album} - {index:03}")
+        };
+
+        // The files of one book aren't a previous run of another...
+        splitter("One").split_file("one.mp3").unwrap();
+        assert!(splitter("Two").previous_run_files("two.mp3").unwrap().is_empty());
+        let written = splitter("Two").split_file("two.mp3").unwrap();
+        assert_eq!(written[0].path, output_dir.join("Two - 001.mp3"));
+        assert_eq!(splitter("One").previous_run_files("one.mp3").unwrap().len(), 2);
+
+        // ...and names that don't tell the books apart find no previous run at all.
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let status_messages = messages.clone();
+        let splitter = Splitter::new(&output_dir).backend(mock_backend()).name_template("{title}")
+            .on_status(move |message| status_messages.lock().unwrap().push(message.to_string()));
+        assert!(splitter.previous_run_files("one.mp3").unwrap().is_empty());
+        splitter.split_file("three.mp3").unwrap();
+        assert!(messages.lock().unwrap().iter().any(|message| message.starts_with("Warning: The names from '{title}'")));
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn parts_with_the_same_name_are_an_error() {
+        let output_dir = test_output_dir("mock-same-name");
+        let backend = mock_backend();
+        let extracted = backend.extracted.clone();
+        let splitter = Splitter::new(&output_dir).backend(backend).name_template("{stem}");
+
+        assert!(splitter.split_file("book.mp3").unwrap_err().contains("would both be written to"));
+        assert!(extracted.lock().unwrap().is_empty());
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn directory_template_gives_each_input_its_own_directory() {
+        let output_dir = test_output_dir("mock-subdir");
+        let splitter = Splitter::new(&output_dir).backend(mock_backend()).directory_template("books/{stem}");
+
+        assert_eq!(splitter.input_output_dir("one.mp3").unwrap(), output_dir.join("books").join("one"));
+        let written = splitter.split_file("one.mp3").unwrap();
+        assert_eq!(written[0].path, output_dir.join("books").join("one").join("one_001.mp3"));
+        let written = splitter.split_file("two.mp3").unwrap();
+        assert_eq!(written[1].path, output_dir.join("books").join("two").join("two_002.mp3"));
+
+        // Previous runs are looked for in the input's own directory.
+        assert_eq!(splitter.previous_run_files("two.mp3").unwrap().len(), 2);
+        assert!(Splitter::new(&output_dir).previous_run_files("two.mp3").unwrap().is_empty());
+
+        // A mirrored input tree puts the template's directories below the input's folder.
+        let splitter = splitter.mirror_input_tree("library");
+        let written = splitter.split_file(Path::new("library/Author/Book/one.mp3")).unwrap();
+        assert_eq!(written[0].path, output_dir.join("Author/Book/books/one/one_001.mp3"));
+        assert_eq!(splitter.input_output_dir("elsewhere/one.mp3").unwrap(), output_dir.join("books/one"));
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn cue_sheet_round_trip_drives_splitting() {
+        let output_dir = test_output_dir("mock-cue");
+        let input = output_dir.join("book.flac");
+        std::fs::write(&input, b"").unwrap();
+
+        let written = Splitter::new(&output_dir).output_mode(OutputMode::CueSheet).backend(mock_backend()).split_file(&input).unwrap();
+        let cue_path = output_dir.join("book.cue");
+        assert!(written.iter().all(|output| output.path == cue_path));
+        assert!(std::fs::read_to_string(&cue_path).unwrap().contains("FILE \"book.flac\" WAVE"));
+
+        let written = Splitter::new(&output_dir).backend(mock_backend()).split_cue_sheet(&cue_path).unwrap();
+        let segments: Vec<(f64, f64)> = written.iter().map(|output| (output.segment.start, output.segment.end)).collect();
+        assert_eq!(segments, vec![(0.0, 11.0), (11.0, 30.0)]);
+        assert_eq!(written[0].path, output_dir.join("book_001.flac"));
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn saved_plan_can_be_edited_and_applied() {
+        let output_dir = test_output_dir("mock-plan");
+        let input = output_dir.join("book.mp3");
+        std::fs::write(&input, b"").unwrap();
+
+        Splitter::new(&output_dir).output_mode(OutputMode::Plan).backend(mock_backend()).split_file(&input).unwrap();
+        let mut plan = SplitPlan::load(output_dir.join("book.plan.json")).unwrap();
+        assert_eq!(plan.split_points, vec![11.0, 30.0]);
+
+        plan.split_points.insert(1, 20.0);
+        let written = Splitter::new(&output_dir).backend(mock_backend()).apply_plan(&plan).unwrap();
+        let segments: Vec<(f64, f64)> = written.iter().map(|output| (output.segment.start, output.segment.end)).collect();
+        assert_eq!(segments, vec![(0.0, 11.0), (11.0, 20.0), (20.0, 30.0)]);
+
+        // Without any silences there is still a plan to add split points to.
+        let silent_backend = MockBackend { silences: Vec::new(), ..mock_backend() };
+        let splitter = Splitter::new(&output_dir).output_mode(OutputMode::Plan).collision_policy(CollisionPolicy::Overwrite).backend(silent_backend);
+        assert_eq!(splitter.split_file(&input).unwrap().len(), 1);
+        assert_eq!(SplitPlan::load(output_dir.join("book.plan.json")).unwrap().split_points, vec![30.0]);
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn segment_length_limits_merge_and_subdivide() {
+        // Detection at the main threshold only finds the 2s silences; the secondary
+        // pass (0.5s) also finds the short pause at 70s.
+        let backend = MockBackend {
+            silences: vec![
+                Silence { start: 2.0, end: 4.0, duration: 2.0 },
+                Silence { start: 40.0, end: 42.0, duration: 2.0 },
+                Silence { start: 70.0, end: 71.0, duration: 1.0 },
+            ],
+            total_duration: 120.0,
+            ..MockBackend::default()
+        };
+
+        let analysis = Splitter::new(".")
+            .silence_threshold_seconds(2.0)
+            .min_segment_seconds(10.0)
+            .max_segment_seconds(60.0)
+            .backend(backend)
+            .analyze("book.mp3")
+            .unwrap();
+        // The 3s intro is merged away, and 41-120 is split at 70.5.
+        assert_eq!(analysis.split_points, vec![41.0, 70.5, 120.0]);
+        assert_eq!(analysis.silences, vec![Silence { start: 40.0, end: 42.0, duration: 2.0 }, Silence { start: 70.0, end: 71.0, duration: 1.0 }]);
+    }
+
+    #[test]
+    fn threshold_search_raises_noise_until_enough_silences() {
+        // Stricter noise thresholds find fewer silences.
+        let backend = MockBackend {
+            total_duration: 100.0,
+            detection: Some(|_silence_threshold_seconds, noise_threshold_db| {
+                let count = if noise_threshold_db < -35.0 { 1 } else { 4 };
+                (1..=count).map(|i| {
+                    let start = i as f64 * 20.0;
+                    Silence { start, end: start + i as f64, duration: i as f64 }
+                }).collect()
+            }),
+            ..MockBackend::default()
+        };
+
+        let search = Splitter::new(".").noise_threshold_db(-40.0).backend(backend).search_thresholds("book.mp3", 4).unwrap();
+        assert!(search.is_exact(4));
+        assert_eq!(search.noise_threshold_db, -35.0);
+        assert_eq!(search.silence_threshold_seconds, 1.5);
+    }
+
+    #[test]
+    fn joined_files_split_as_one_book() {
+        let output_dir = test_output_dir("continuous");
+        // Each file is 50s long; the chapter break sits in the silence around the end of the first file.
+        let backend = MockBackend { silences: Vec::new(), total_duration: 50.0, ..MockBackend::default() };
+        let extracted = backend.extracted.clone();
+        let splitter = Splitter::new(&output_dir).backend(backend);
+
+        let list_path = splitter.join_files("book", &[PathBuf::from("disc1.mp3"), PathBuf::from("disc2.mp3")]).unwrap();
+        assert_eq!(list_path, output_dir.join("book.ffconcat"));
+        let written = splitter.split_with_analysis(&list_path, &Analysis::new(
+            vec![Silence { start: 49.0, end: 52.0, duration: 3.0 }], 100.0, 2.0)).unwrap();
+
+        let names: Vec<_> = written.iter().map(|output| output.path.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert_eq!(names, vec!["book_001.mp3", "book_002.mp3"]);
+        assert_eq!(written[1].segment.start, 50.5);
+        assert_eq!(extracted.lock().unwrap().len(), 2);
+        assert!(std::fs::read_to_string(&list_path).unwrap().contains("disc2.mp3'\nduration 50\n"));
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn chaptered_m4b_mode_writes_a_single_file() {
+        let output_dir = test_output_dir("mock-m4b");
+        let backend = MockBackend {
+            silences: vec![Silence { start: 10.0, end: 12.0, duration: 2.0 }, Silence { start: 20.0, end: 22.0, duration: 2.0 }],
+            ..mock_backend()
+        };
+        let extracted = backend.extracted.clone();
+        let splitter = Splitter::new(&output_dir).output_mode(OutputMode::ChapteredM4b).backend(backend);
+
+        let written = splitter.split_file("book.mp3").unwrap();
+        assert_eq!(written.len(), 3);
+        assert!(written.iter().all(|output| output.path == output_dir.join("book.m4b")));
+        assert_eq!(extracted.lock().unwrap().len(), 3);
+
+        // By default a second run stops; continuing replaces the file, saying so.
+        assert!(splitter.split_file("book.mp3").is_err());
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let status_messages = messages.clone();
+        let splitter = splitter.collision_policy(CollisionPolicy::Continue)
+            .on_status(move |message| status_messages.lock().unwrap().push(message.to_string()));
+        assert_eq!(splitter.split_file("book.mp3").unwrap().len(), 3);
+        assert!(messages.lock().unwrap().iter().any(|message| message.starts_with("Status: Replacing")));
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn segments_are_tagged_from_the_title_template() {
+        let output_dir = test_output_dir("mock-tags");
+        let backend = MockBackend {
+            tags: Tags { entries: vec![("album".to_string(), "Book".to_string()), ("artist".to_string(), "Author".to_string())], cover_art: true },
+            ..mock_backend()
+        };
+        let tagged_parts = backend.tagged.clone();
+        let splitter = Splitter::new(&output_dir).title_template("{album} {index}/{total}").backend(backend);
+
+        let written = splitter.split_file("book.mp3").unwrap();
+        let tagged = tagged_parts.lock().unwrap();
+        assert_eq!(tagged.len(), 2);
+        assert_eq!(tagged[1].0, written[1].path);
+        assert_eq!(tagged[1].1.get("title"), Some("Book 2/2"));
+        assert_eq!(tagged[1].1.get("track"), Some("2/2"));
+        assert_eq!(tagged[1].1.get("artist"), Some("Author"));
+        assert!(tagged[1].1.cover_art);
+
+        drop(tagged);
+
+        // Continued parts are numbered like their files.
+        let splitter = splitter.collision_policy(CollisionPolicy::Continue);
+        splitter.split_file("book.mp3").unwrap();
+        let tagged = tagged_parts.lock().unwrap();
+        assert_eq!(tagged[3].0, output_dir.join("book_004.mp3"));
+        assert_eq!(tagged[3].1.get("title"), Some("Book 4/4"));
+        assert_eq!(tagged[3].1.get("track"), Some("4/4"));
+        drop(tagged);
+
+        // A bad template fails before anything is cut.
+        assert!(splitter.title_template("{bogus}").split_file("book.mp3").is_err());
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn accurate_mode_uses_the_accurate_cut() {
+        // Only supports accurate cuts, so copying would fail the split.
+        let output_dir = test_output_dir("mock-accurate");
+        let backend = MockBackend { stream_copy_fails: true, ..mock_backend() };
+        let extracted = backend.extracted.clone();
+        let splitter = Splitter::new(&output_dir).backend(backend);
+        assert!(splitter.split_file("book.mp3").is_err());
+
+        splitter.cut_mode(CutMode::Accurate).split_file("book.mp3").unwrap();
+        let cut: Vec<usize> = extracted.lock().unwrap().iter().map(|(_, segment, _)| segment.number).collect();
+        assert_eq!(cut, vec![1, 2]);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn progress_and_silences_are_reported_while_running() {
+        let output_dir = test_output_dir("mock-progress");
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let progress_reports = reports.clone();
+        Splitter::new(&output_dir)
+            .backend(mock_backend())
+            .on_progress(move |progress| progress_reports.lock().unwrap().push((progress.stage, progress.fraction())))
+            .on_silence(|silence| assert_eq!(silence.start, 10.0))
+            .split_file("book.mp3")
+            .unwrap();
+
+        // Both passes end with a complete report.
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.first(), Some(&(ProgressStage::Detecting, 1.0)));
+        assert_eq!(reports.last(), Some(&(ProgressStage::Splitting, 1.0)));
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn videos_are_split_from_the_chosen_audio_stream() {
+        let output_dir = test_output_dir("video-split");
+        // A video with German and English audio.
+        let stream = |codec: &str, language: &str, default: bool| AudioStream {
+            codec: codec.to_string(),
+            language: Some(language.to_string()),
+            title: None,
+            default,
+        };
+        let backend = MockBackend {
+            streams: Some(MediaStreams {
+                format_name: "matroska,webm".to_string(),
+                audio: vec![stream("aac", "ger", true), stream("opus", "eng", false)],
+                video: true,
+            }),
+            ..mock_backend()
+        };
+        let extracted_streams = backend.extracted_streams.clone();
+        let extracted = backend.extracted.clone();
+        let splitter = Splitter::new(&output_dir)
+            .backend(backend)
+            .audio_stream(AudioStreamChoice::Language("ENG".to_string()))
+            .tag_segments(false);
+
+        // The English Opus stream is copied as it is...
+        let written = splitter.split_file("lecture.mkv").unwrap();
+        assert_eq!(written[0].path, output_dir.join("lecture_001.opus"));
+        let (stream, audio, encode) = extracted_streams.lock().unwrap()[0].clone();
+        assert_eq!((stream, encode), (1, false));
+        assert!(extracted.lock().unwrap().iter().all(|(input, _, _)| *input == audio));
+        // ...and removed once it has been split.
+        assert!(!audio.exists());
+
+        // With an output format, the stream is converted to it.
+        let written = splitter.output_format(".MP3").collision_policy(CollisionPolicy::Overwrite).split_file("lecture.mkv").unwrap();
+        assert_eq!(written[0].path, output_dir.join("lecture_001.mp3"));
+        assert!(extracted_streams.lock().unwrap()[1].2);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn segments_follow_split_points() {
+        let silences = vec![
+            Silence { start: 10.0, end: 12.0, duration: 2.0 },
+            Silence { start: 20.0, end: 20.5, duration: 0.5 },
+        ];
+        let analysis = Analysis::new(silences, 30.0, 1.0);
+        assert_eq!(analysis.silences.len(), 1);
+        assert_eq!(analysis.segments(), vec![
+            Segment { number: 1, start: 0.0, end: 11.0, title: None },
+            Segment { number: 2, start: 11.0, end: 30.0, title: None },
+        ]);
+    }
+
+    #[test]
+    fn chapters_become_titled_segments() {
+        let chapters = [
+            Chapter { start: 0.0, end: 60.0, title: Some("Intro".to_string()) },
+            Chapter { start: 60.0, end: 119.9, title: None },
+        ];
+        let analysis = Analysis::from_chapters(&chapters, 120.0);
+        assert!(analysis.has_split_points());
+        assert_eq!(analysis.segments(), vec![
+            Segment { number: 1, start: 0.0, end: 60.0, title: Some("Intro".to_string()) },
+            Segment { number: 2, start: 60.0, end: 120.0, title: None },
+        ]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+
+use crate::chapters::unquote_flat_value;
+
+/// The streams of a media file, as far as splitting it is concerned.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaStreams {
+    pub format_name: String,     // FFmpeg's names of the container format, e.g. "matroska,webm".
+    pub audio: Vec<AudioStream>, // The audio streams in file order.
+    pub video: bool,             // Whether there is a video stream; cover art doesn't count.
+}
+
+/// One audio stream of a media file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioStream {
+    pub codec: String,            // FFmpeg's codec name, e.g. "aac" or "opus".
+    pub language: Option<String>, // The language tag, usually an ISO 639-2 code like "eng".
+    pub title: Option<String>,    // The title tag, e.g. "Commentary".
+    pub default: bool,            // Whether the file marks it as the default audio stream.
+}
+
+impl AudioStream {
+    /// A short description for listing the streams of a file, e.g. `eng, aac, "Commentary"`.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self.language.clone().unwrap_or_else(|| "unknown language".to_string()), self.codec.clone()];
+        if let Some(title) = &self.title {
+            parts.push(format!("\"{}\"", title));
+        }
+        if self.default {
+            parts.push("default".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+impl MediaStreams {
+    /// The position among the audio streams of the one that is split unless another is
+    /// chosen: the default stream, or else the first.
+    pub fn default_audio_stream(&self) -> usize {
+        self.audio.iter().position(|stream| stream.default).unwrap_or(0)
+    }
+
+    /// The extension of a format that takes audio stream `stream` as it is.
+    pub fn audio_extension(&self, stream: usize) -> Option<&'static str> {
+        self.audio.get(stream).map(|audio| codec_extension(&audio.codec, &self.format_name))
+    }
+}
+
+/// Which audio stream of a file with several is split.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioStreamChoice {
+    /// The audio stream at this position, counting from 1.
+    Number(usize),
+    /// The first audio stream with this language tag (compared case-insensitively).
+    Language(String),
+}
+
+impl AudioStreamChoice {
+    /// Reads a stream number like `2` or a language code like `eng`.
+    pub fn parse(value: &str) -> Result<AudioStreamChoice, String> {
+        let value = value.trim();
+        match value.parse::<usize>() {
+            Ok(0) => Err("Audio streams are counted from 1.".to_string()),
+            Ok(number) => Ok(AudioStreamChoice::Number(number)),
+            Err(_) if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphabetic() || c == '-') => {
+                Ok(AudioStreamChoice::Language(value.to_string()))
+            }
+            Err(_) => Err(format!("Invalid audio stream '{}'. Please enter a stream number like 2 or a language code like eng.", value)),
+        }
+    }
+
+    /// Returns the position of the chosen stream among the audio streams of `streams`.
+    pub fn select(&self, streams: &MediaStreams) -> Result<usize, String> {
+        let available = || {
+            streams.audio.iter().enumerate().map(|(i, stream)| format!("{}: {}", i + 1, stream.describe())).collect::<Vec<_>>().join("; ")
+        };
+        match self {
+            AudioStreamChoice::Number(number) if *number >= 1 && *number <= streams.audio.len() => Ok(number - 1),
+            AudioStreamChoice::Number(number) => {
+                Err(format!("There is no audio stream {}. The audio streams are {}.", number, available()))
+            }
+            AudioStreamChoice::Language(language) => streams
+                .audio
+                .iter()
+                .position(|stream| stream.language.as_deref().is_some_and(|tag| tag.eq_ignore_ascii_case(language)))
+                .ok_or_else(|| format!("There is no audio stream in the language '{}'. The audio streams are {}.", language, available())),
+        }
+    }
+}
+
+/// Parses the output of `ffprobe -show_entries format=format_name:stream=codec_type,codec_name:
+/// stream_tags=language,title:stream_disposition=default,attached_pic -of flat`.
+pub fn parse_ffprobe_streams(output: &str) -> Result<MediaStreams, String> {
+    let mut format_name = String::new();
+    let mut streams: BTreeMap<usize, ProbedStream> = BTreeMap::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else { continue };
+        let value = unquote_flat_value(value);
+        if key == "format.format_name" {
+            format_name = value;
+        } else if let Some((index, field)) = key.strip_prefix("streams.stream.").and_then(|rest| rest.split_once('.')) {
+            let index = index.parse::<usize>().map_err(|e| format!("Invalid stream index '{}': {}", index, e))?;
+            let stream = streams.entry(index).or_default();
+            match field {
+                "codec_type" => stream.codec_type = value,
+                "codec_name" => stream.audio.codec = value,
+                "disposition.default" => stream.audio.default = value == "1",
+                "disposition.attached_pic" => stream.attached_pic = value == "1",
+                "tags.language" => stream.audio.language = Some(value).filter(|language| language != "und"),
+                "tags.title" => stream.audio.title = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(MediaStreams {
+        format_name,
+        video: streams.values().any(|stream| stream.codec_type == "video" && !stream.attached_pic),
+        audio: streams.into_values().filter(|stream| stream.codec_type == "audio").map(|stream| stream.audio).collect(),
+    })
+}
+
+// What `parse_ffprobe_streams` collects about each stream.
+#[derive(Default)]
+struct ProbedStream {
+    codec_type: String,
+    attached_pic: bool,
+    audio: AudioStream,
+}
+
+// The extension of a format that takes audio of `codec` from a `format_name` container as is.
+pub(crate) fn codec_extension(codec: &str, format_name: &str) -> &'static str {
+    match codec {
+        "mp3" => "mp3",
+        "flac" => "flac",
+        "opus" => "opus",
+        "ac3" => "ac3",
+        "eac3" => "eac3",
+        "aac" | "alac" => "m4a",
+        "vorbis" => "ogg",
+        "wmav1" | "wmav2" | "wmapro" | "wmalossless" => "wma",
+        pcm if pcm.starts_with("pcm_") && format_name.split(',').any(|name| name == "aiff") => "aiff",
+        pcm if pcm.starts_with("pcm_") => "wav",
+        // Matroska takes nearly every codec.
+        _ => "mka",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_streams_of_a_video() {
+        // A lecture recording with cover art, German and English audio, and subtitles.
+        let output = r#"streams.stream.0.codec_name="h264"
+streams.stream.0.codec_type="video"
+streams.stream.0.disposition.default=1
+streams.stream.0.disposition.attached_pic=0
+streams.stream.1.codec_name="aac"
+streams.stream.1.codec_type="audio"
+streams.stream.1.disposition.default=1
+streams.stream.1.disposition.attached_pic=0
+streams.stream.1.tags.language="ger"
+streams.stream.2.codec_name="opus"
+streams.stream.2.codec_type="audio"
+streams.stream.2.disposition.default=0
+streams.stream.2.disposition.attached_pic=0
+streams.stream.2.tags.language="eng"
+streams.stream.2.tags.title="Interpreter"
+streams.stream.3.codec_name="subrip"
+streams.stream.3.codec_type="subtitle"
+format.format_name="matroska,webm"
+"#;
+        let streams = parse_ffprobe_streams(output).unwrap();
+        assert!(streams.video);
+        assert_eq!(streams.audio.len(), 2);
+        assert_eq!(streams.default_audio_stream(), 0);
+        assert_eq!(streams.audio[1].describe(), "eng, opus, \"Interpreter\"");
+        assert_eq!(streams.audio_extension(0), Some("m4a"));
+        assert_eq!(streams.audio_extension(1), Some("opus"));
+
+        assert_eq!(AudioStreamChoice::parse("ENG").unwrap().select(&streams), Ok(1));
+        assert_eq!(AudioStreamChoice::parse("1").unwrap().select(&streams), Ok(0));
+        assert!(AudioStreamChoice::parse("3").unwrap().select(&streams).is_err());
+        assert!(AudioStreamChoice::parse("fra").unwrap().select(&streams).is_err());
+        assert!(AudioStreamChoice::parse("0").is_err());
+        assert!(AudioStreamChoice::parse("a:1").is_err());
+    }
+
+    #[test]
+    fn cover_art_is_no_video() {
+        let output = r#"streams.stream.0.codec_name="mp3"
+streams.stream.0.codec_type="audio"
+streams.stream.0.tags.language="und"
+streams.stream.1.codec_name="mjpeg"
+streams.stream.1.codec_type="video"
+streams.stream.1.disposition.attached_pic=1
+format.format_name="mp3"
+"#;
+        let streams = parse_ffprobe_streams(output).unwrap();
+        assert!(!streams.video);
+        assert_eq!(streams.audio, vec![AudioStream { codec: "mp3".to_string(), ..AudioStream::default() }]);
+        assert_eq!(parse_ffprobe_streams("").unwrap(), MediaStreams::default());
+    }
+
+    #[test]
+    fn audio_format_follows_the_codec() {
+        assert_eq!(codec_extension("aac", "mov,mp4,m4a,3gp,3g2,mj2"), "m4a");
+        assert_eq!(codec_extension("pcm_s16be", "aiff"), "aiff");
+        assert_eq!(codec_extension("pcm_s16le", "wav"), "wav");
+        assert_eq!(codec_extension("wmav2", "asf"), "wma");
+        assert_eq!(codec_extension("truehd", "matroska,webm"), "mka");
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+
+use crate::chapters::unquote_flat_value;
+use crate::splitter::Segment;
+use crate::template::render_template;
+
+/// The default template for the title tag of a segment: its chapter title, or "Part N".
+pub const DEFAULT_TITLE_TEMPLATE: &str = "{title}";
+
+// Source tags that describe the whole input and would be wrong on a single segment.
+const WHOLE_FILE_TAGS: &[&str] = &[
+    "track", "tracktotal", "totaltracks", "cuesheet", "itunsmpb", "duration",
+    // Written anew by the muxer.
+    "encoder", "major_brand", "minor_version", "compatible_brands",
+];
+
+/// The metadata tags of an audio file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tags {
+    pub entries: Vec<(String, String)>, // Key/value pairs in FFmpeg's naming; keys compare case-insensitively.
+    pub cover_art: bool,                // Whether the file has (or should get) cover art.
+}
+
+impl Tags {
+    /// The value of the tag `key`, if the file has it.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)).map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the tag `key` to `value`, replacing any value it had.
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        self.remove(key);
+        self.entries.push((key.to_string(), value.into()));
+    }
+
+    /// Removes the tag `key`.
+    pub fn remove(&mut self, key: &str) {
+        self.entries.retain(|(name, _)| !name.eq_ignore_ascii_case(key));
+    }
+
+    /// The album the input belongs to. Single-file books often only carry their
+    /// name in the title tag, which then names the album of the segments.
+    pub fn album(&self) -> Option<&str> {
+        self.get("album").or_else(|| self.get("title"))
+    }
+
+    /// The artist of the input, falling back to the album artist.
+    pub fn artist(&self) -> Option<&str> {
+        self.get("artist").or_else(|| self.get("album_artist"))
+    }
+
+    /// The tags of segment number `track` of `total` cut from a file with these tags:
+    /// the given title, the track number as "n/total", and the album, artist, cover art
+    /// and other tags of the whole file.
+    pub fn for_segment(&self, track: usize, total: usize, title: &str) -> Tags {
+        let mut tags = Tags {
+            entries: self
+                .entries
+                .iter()
+                .filter(|(name, _)| !WHOLE_FILE_TAGS.iter().any(|tag| name.eq_ignore_ascii_case(tag)))
+                .cloned()
+                .collect(),
+            cover_art: self.cover_art,
+        };
+        if let Some(album) = self.album() {
+            tags.set("album", album);
+        }
+        if let Some(artist) = self.artist() {
+            tags.set("artist", artist);
+        }
+        tags.set("title", title);
+        tags.set("track", format!("{}/{}", track, total));
+        tags
+    }
+}
+
+/// Renders the title tag of `segment`, track number `track` of `total` cut from the file
+/// named `stem` with the tags `source`.
+///
+/// The template can use `{title}` (the chapter title, or "Part N"), `{index}` (the track
+/// number), `{total}`, `{album}`, `{artist}` and `{stem}`.
+pub fn format_segment_title(template: &str, segment: &Segment, track: usize, total: usize, stem: &str, source: &Tags) -> Result<String, String> {
+    render_template(template, &|name| match name {
+        "title" => Some(segment.title.clone().unwrap_or_else(|| format!("Part {}", track))),
+        "index" => Some(track.to_string()),
+        "total" => Some(total.to_string()),
+        "album" => Some(source.album().unwrap_or_default().to_string()),
+        "artist" => Some(source.artist().unwrap_or_default().to_string()),
+        "stem" => Some(stem.to_string()),
+        _ => None,
+    })
+}
+
+/// Checks that `template` is a valid title template for [`format_segment_title`].
+pub fn validate_title_template(template: &str) -> Result<(), String> {
+    let segment = Segment { number: 1, start: 0.0, end: 1.0, title: None };
+    format_segment_title(template, &segment, 1, 1, "", &Tags::default()).map(|_| ())
+}
+
+/// Parses the output of
+/// `ffprobe -show_entries format_tags:stream=codec_type:stream_tags:stream_disposition=attached_pic -of flat`.
+///
+/// Containers like Ogg keep their tags on the audio stream rather than the file, so the
+/// tags of the first audio stream fill in for missing file tags.
+pub fn parse_ffprobe_tags(output: &str) -> Result<Tags, String> {
+    let mut tags = Tags::default();
+    let mut streams: BTreeMap<usize, ProbedStream> = BTreeMap::new();
+
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else { continue };
+        let value = unquote_flat_value(value);
+
+        if let Some(name) = key.strip_prefix("format.tags.") {
+            tags.entries.push((name.to_string(), value));
+        } else if let Some(rest) = key.strip_prefix("streams.stream.") {
+            let Some((index, field)) = rest.split_once('.') else { continue };
+            let index = index.parse::<usize>().map_err(|e| format!("Invalid stream index '{}': {}", index, e))?;
+            let stream = streams.entry(index).or_default();
+            match field {
+                "codec_type" => stream.codec_type = value,
+                "disposition.attached_pic" => stream.attached_pic = value == "1",
+                _ => {
+                    if let Some(name) = field.strip_prefix("tags.") {
+                        stream.tags.push((name.to_string(), value));
+                    }
+                }
+            }
+        }
+    }
+
+    tags.cover_art = streams.values().any(|stream| stream.attached_pic);
+    if let Some(audio_stream) = streams.into_values().find(|stream| stream.codec_type == "audio") {
+        for (name, value) in audio_stream.tags {
+            if tags.get(&name).is_none() {
+                tags.entries.push((name, value));
+            }
+        }
+    }
+    Ok(tags)
+}
+
+// What `parse_ffprobe_tags` needs to know about each stream.
+#[derive(Default)]
+struct ProbedStream {
+    codec_type: String,
+    attached_pic: bool,
+    tags: Vec<(String, String)>,
+}
+
+// Encodes `image` as a base64 FLAC picture block, the form in which Ogg files carry
+// their cover art (as the `METADATA_BLOCK_PICTURE` comment).
+pub(crate) fn metadata_block_picture(image: &[u8]) -> String {
+    let mime_type: &[u8] = if image.starts_with(b"\x89PNG") {
+        b"image/png"
+    } else if image.starts_with(b"GIF8") {
+        b"image/gif"
+    } else {
+        b"image/jpeg"
+    };
+
+    let mut block = Vec::with_capacity(image.len() + 64);
+    block.extend_from_slice(&3u32.to_be_bytes()); // Picture type: front cover.
+    block.extend_from_slice(&(mime_type.len() as u32).to_be_bytes());
+    block.extend_from_slice(mime_type);
+    block.extend_from_slice(&0u32.to_be_bytes()); // No description.
+    // Width, height, color depth and palette size are unknown; players read them from the image.
+    block.extend_from_slice(&[0; 16]);
+    block.extend_from_slice(&(image.len() as u32).to_be_bytes());
+    block.extend_from_slice(image);
+    encode_base64(&block)
+}
+
+// Standard base64 with padding.
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_file_and_stream_tags() {
+        let output = r#"streams.stream.0.codec_type="audio"
+streams.stream.0.disposition.attached_pic=0
+streams.stream.0.tags.ARTIST="Stream Artist"
+streams.stream.0.tags.GENRE="Audiobook"
+streams.stream.1.codec_type="video"
+streams.stream.1.disposition.attached_pic=1
+streams.stream.1.tags.comment="Cover (front)"
+format.tags.title="The \"Long\" Book"
+format.tags.artist="Jane Doe"
+format.tags.track="1"
+"#;
+        let tags = parse_ffprobe_tags(output).unwrap();
+        assert!(tags.cover_art);
+        assert_eq!(tags.get("TITLE"), Some("The \"Long\" Book"));
+        assert_eq!(tags.get("artist"), Some("Jane Doe"));
+        assert_eq!(tags.get("genre"), Some("Audiobook"));
+        assert_eq!(tags.get("comment"), None);
+        assert_eq!(parse_ffprobe_tags("").unwrap(), Tags::default());
+    }
+
+    #[test]
+    fn segments_get_their_own_title_and_track() {
+        let source = Tags {
+            entries: vec![
+                ("title".to_string(), "The Long Book".to_string()),
+                ("album_artist".to_string(), "Jane Doe".to_string()),
+                ("date".to_string(), "2021".to_string()),
+                ("track".to_string(), "1".to_string()),
+            ],
+            cover_art: true,
+        };
+        let segment = Segment { number: 3, start: 60.0, end: 90.0, title: None };
+        let title = format_segment_title("{album} - {title} ({index}/{total}, {stem})", &segment, 3, 12, "book", &source).unwrap();
+        assert_eq!(title, "The Long Book - Part 3 (3/12, book)");
+
+        let tags = source.for_segment(3, 12, &title);
+        assert_eq!(tags.get("title"), Some(title.as_str()));
+        assert_eq!(tags.get("track"), Some("3/12"));
+        assert_eq!(tags.get("album"), Some("The Long Book"));
+        assert_eq!(tags.get("artist"), Some("Jane Doe"));
+        assert_eq!(tags.get("date"), Some("2021"));
+        assert!(tags.cover_art);
+
+        assert!(validate_title_template(DEFAULT_TITLE_TEMPLATE).is_ok());
+        assert!(validate_title_template("{chapter}").is_err());
+    }
+
+    #[test]
+    fn cover_art_becomes_a_picture_block() {
+        assert_eq!(encode_base64(b"Man"), "TWFu");
+        assert_eq!(encode_base64(b"Ma"), "TWE=");
+        assert_eq!(encode_base64(b"M"), "TQ==");
+
+        // type 3, "image/png", no description, no dimensions, 4 bytes of data.
+        let block = metadata_block_picture(b"\x89PNG");
+        let mut expected = vec![0, 0, 0, 3, 0, 0, 0, 9];
+        expected.extend_from_slice(b"image/png");
+        expected.extend_from_slice(&[0; 20]);
+        expected.extend_from_slice(&[0, 0, 0, 4]);
+        expected.extend_from_slice(b"\x89PNG");
+        assert_eq!(block, encode_base64(&expected));
+    }
+}
@@ -0,0 +1,102 @@
+/// One piece of a parsed template.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TemplatePiece<'a> {
+    Literal(String),
+    // A `{name}` placeholder; `{name:03}` zero-pads its value to the given width.
+    Placeholder { name: &'a str, width: usize },
+}
+
+/// Splits `template` into literal text and `{name}` placeholders. `{{` and `}}` stand
+/// for literal braces.
+pub(crate) fn parse_template(template: &str) -> Result<Vec<TemplatePiece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '{' if rest.starts_with("{{") => {
+                literal.push('{');
+                rest = &rest[2..];
+            }
+            '{' => {
+                let Some(close) = rest.find('}') else {
+                    return Err(format!("Unclosed placeholder in template '{}'.", template));
+                };
+                let (name, width) = match rest[1..close].split_once(':') {
+                    Some((name, width)) => match width.parse::<usize>() {
+                        Ok(width) => (name, width),
+                        Err(_) => return Err(format!("Invalid padding '{}' in template '{}'. Use e.g. '{{{}:03}}'.", width, template, name)),
+                    },
+                    None => (&rest[1..close], 0),
+                };
+                if !literal.is_empty() {
+                    pieces.push(TemplatePiece::Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(TemplatePiece::Placeholder { name, width });
+                rest = &rest[close + 1..];
+            }
+            '}' if rest.starts_with("}}") => {
+                literal.push('}');
+                rest = &rest[2..];
+            }
+            '}' => return Err(format!("Unmatched '}}' in template '{}'. Write '}}}}' for a literal brace.", template)),
+            c => {
+                literal.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(TemplatePiece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// Fills the placeholders of `template` with `value(name)`, zero-padding them where the
+/// template asks for it. Placeholders `value` doesn't know are an error.
+pub(crate) fn render_template(template: &str, value: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut rendered = String::with_capacity(template.len());
+    for piece in parse_template(template)? {
+        match piece {
+            TemplatePiece::Literal(text) => rendered.push_str(&text),
+            TemplatePiece::Placeholder { name, width } => {
+                let Some(replacement) = value(name) else {
+                    return Err(format!("Unknown placeholder '{{{}}}' in template '{}'.", name, template));
+                };
+                rendered.push_str(&format!("{:0>width$}", replacement, width = width));
+            }
+        }
+    }
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders_and_escaped_braces() {
+        let value = |name: &str| match name {
+            "index" => Some("7".to_string()),
+            "title" => Some("Intro".to_string()),
+            _ => None,
+        };
+        assert_eq!(render_template("{{{index}}} {title}", &value).unwrap(), "{7} Intro");
+        assert_eq!(render_template("{index:03}_{index:2}_{index:0}", &value).unwrap(), "007_07_7");
+        assert!(render_template("{bogus}", &value).is_err());
+        assert!(render_template("{index", &value).is_err());
+        assert!(render_template("index}", &value).is_err());
+        assert!(render_template("{index:x}", &value).is_err());
+    }
+
+    #[test]
+    fn splits_literals_and_placeholders() {
+        assert_eq!(parse_template("Ä{stem}_{index:03}.").unwrap(), vec![
+            TemplatePiece::Literal("Ä".to_string()),
+            TemplatePiece::Placeholder { name: "stem", width: 0 },
+            TemplatePiece::Literal("_".to_string()),
+            TemplatePiece::Placeholder { name: "index", width: 3 },
+            TemplatePiece::Literal(".".to_string()),
+        ]);
+    }
+}